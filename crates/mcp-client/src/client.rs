@@ -1,107 +1,65 @@
 use crate::error::{ClientError, Result};
-use crate::transport::{create_transport, McpClientTransport, TransportConfig};
+use crate::pagination::{Page, DEFAULT_MAX_PAGES};
+use crate::protocol::{protocol_version_of, require_capability, VersionPolicy};
+use crate::transport::{
+    create_transport, InboundReceiver, InboundSender, McpClientTransport, TransportConfig,
+};
+use futures::{stream, Stream, TryStreamExt};
 use mcp_types::{McpClient, TransportType};
 use rmcp::model::{
-    ClientCapabilities, Implementation, InitializeRequestParam, InitializeResult, ProtocolVersion,
+    ClientCapabilities, CompleteRequestParam, CompleteResult, EmptyResult, GetPromptRequestParam, GetPromptResult,
+    Implementation, InitializeRequestParam, InitializeResult, ListPromptsResult, ListResourceTemplatesResult,
+    ListResourcesResult, ListToolsResult, LoggingLevel, PaginatedRequestParam, ProtocolVersion, Resource,
+    ServerCapabilities, SetLevelRequestParam, SubscribeRequestParam, Tool, UnsubscribeRequestParam,
 };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 use tracing::{error, info, warn};
 
 pub struct McpRemoteClient {
     transports: Vec<(TransportType, TransportConfig)>,
-    current_transport: Arc<Mutex<Option<Box<dyn McpClientTransport>>>>,
+    current_transport: Arc<Mutex<Option<Arc<dyn McpClientTransport>>>>,
     current_transport_index: Arc<Mutex<usize>>,
+    connect_lock: Arc<Mutex<()>>,
+    /// Indices of HTTP transports whose server turned out to speak legacy HTTP+SSE
+    legacy_sse: Arc<Mutex<HashSet<usize>>>,
     initialized: Arc<Mutex<bool>>,
     client_info: Implementation,
     capabilities: ClientCapabilities,
+    versions: VersionPolicy,
+    /// The version the server chose in `initialize`
+    protocol_version: Arc<Mutex<Option<ProtocolVersion>>>,
+    /// What the server declared in `initialize`; `None` if it did not say
+    server_capabilities: Arc<Mutex<Option<ServerCapabilities>>>,
     request_id: Arc<Mutex<u64>>,
+    inbound_tx: InboundSender,
+    inbound_rx: Arc<Mutex<Option<InboundReceiver>>>,
 }
 
 impl McpRemoteClient {
     pub fn new(primary_endpoint: String, fallback_transports: Vec<TransportType>) -> Self {
-        let mut transports = vec![];
-
         // Primary transport (HTTP by default)
         let primary_config = TransportConfig {
             endpoint: primary_endpoint,
             ..Default::default()
         };
-        transports.push((TransportType::Http, primary_config));
-
-        // Add fallback transports
-        for transport_type in fallback_transports {
-            let config = match transport_type {
-                TransportType::Stdio => TransportConfig {
-                    endpoint: "mcp-server".to_string(), // Default server command
-                    ..Default::default()
-                },
-                TransportType::Tcp => TransportConfig {
-                    endpoint: "8080".to_string(), // Default port
-                    ..Default::default()
-                },
-                TransportType::Http => continue, // Skip if already added as primary
-            };
-            transports.push((transport_type, config));
-        }
-
-        let client_info = Implementation {
-            name: "mcp-connect-client".to_string(),
-            version: "0.1.0".to_string(),
-            title: None,
-            icons: None,
-            website_url: None,
-        };
-
-        let capabilities = ClientCapabilities::builder()
-            .enable_experimental()
-            .enable_roots()
-            .enable_roots_list_changed()
-            .build();
 
-        Self {
-            transports,
-            current_transport: Arc::new(Mutex::new(None)),
-            current_transport_index: Arc::new(Mutex::new(0)),
-            initialized: Arc::new(Mutex::new(false)),
-            client_info,
-            capabilities,
-            request_id: Arc::new(Mutex::new(1)),
-        }
+        Self::new_with_config(primary_config, fallback_transports)
     }
 
     pub async fn with_custom_transports(transports: Vec<(TransportType, TransportConfig)>) -> Self {
-        let client_info = Implementation {
-            name: "mcp-connect-client".to_string(),
-            version: "0.1.0".to_string(),
-            title: None,
-            icons: None,
-            website_url: None,
-        };
-
-        let capabilities = ClientCapabilities::builder()
-            .enable_experimental()
-            .enable_roots()
-            .enable_roots_list_changed()
-            .build();
-
-        Self {
-            transports,
-            current_transport: Arc::new(Mutex::new(None)),
-            current_transport_index: Arc::new(Mutex::new(0)),
-            initialized: Arc::new(Mutex::new(false)),
-            client_info,
-            capabilities,
-            request_id: Arc::new(Mutex::new(1)),
-        }
+        Self::from_transports(transports)
     }
 
     pub fn new_with_config(primary_config: TransportConfig, fallback_transports: Vec<TransportType>) -> Self {
         let mut transports = vec![];
 
         // Primary transport (HTTP by default)
-        transports.push((TransportType::Http, primary_config));
+        transports.push((TransportType::Http, primary_config.clone()));
 
         // Add fallback transports
         for transport_type in fallback_transports {
@@ -114,11 +72,55 @@ impl McpRemoteClient {
                     endpoint: "8080".to_string(), // Default port
                     ..Default::default()
                 },
+                TransportType::Unix => TransportConfig {
+                    endpoint: "unix:/tmp/mcp-server.sock".to_string(), // Default socket path
+                    ..Default::default()
+                },
+                TransportType::Sse => primary_config.clone(), // Same server, legacy protocol
+                TransportType::WebSocket => TransportConfig {
+                    endpoint: websocket_url(&primary_config.endpoint),
+                    ..primary_config.clone()
+                },
                 TransportType::Http => continue, // Skip if already added as primary
             };
             transports.push((transport_type, config));
         }
 
+        Self::from_transports(transports)
+    }
+
+    /// Replaces the default settings of a fallback transport, such as the STDIO server command.
+    pub fn with_fallback_config(mut self, transport_type: TransportType, config: TransportConfig) -> Self {
+        let kind = std::mem::discriminant(&transport_type);
+        for (existing_type, existing_config) in self.transports.iter_mut().skip(1) {
+            if std::mem::discriminant(existing_type) == kind {
+                *existing_config = config.clone();
+            }
+        }
+        self
+    }
+
+    /// Sets the protocol versions offered in and accepted from `initialize`.
+    pub fn with_version_policy(mut self, versions: VersionPolicy) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    pub fn version_policy(&self) -> &VersionPolicy {
+        &self.versions
+    }
+
+    /// The protocol version negotiated with the server, once initialized.
+    pub async fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.protocol_version.lock().await.clone()
+    }
+
+    /// The capabilities the server declared in `initialize`, once initialized.
+    pub async fn server_capabilities(&self) -> Option<ServerCapabilities> {
+        self.server_capabilities.lock().await.clone()
+    }
+
+    fn from_transports(transports: Vec<(TransportType, TransportConfig)>) -> Self {
         let client_info = Implementation {
             name: "mcp-connect-client".to_string(),
             version: "0.1.0".to_string(),
@@ -133,35 +135,64 @@ impl McpRemoteClient {
             .enable_roots_list_changed()
             .build();
 
+        let (inbound_tx, inbound_rx) = mpsc::unbounded_channel();
+
         Self {
             transports,
             current_transport: Arc::new(Mutex::new(None)),
             current_transport_index: Arc::new(Mutex::new(0)),
+            connect_lock: Arc::new(Mutex::new(())),
+            legacy_sse: Arc::new(Mutex::new(HashSet::new())),
             initialized: Arc::new(Mutex::new(false)),
             client_info,
             capabilities,
+            versions: VersionPolicy::default(),
+            protocol_version: Arc::new(Mutex::new(None)),
+            server_capabilities: Arc::new(Mutex::new(None)),
             request_id: Arc::new(Mutex::new(1)),
+            inbound_tx,
+            inbound_rx: Arc::new(Mutex::new(Some(inbound_rx))),
         }
     }
 
-    async fn next_request_id(&self) -> u64 {
+    /// Takes the stream of requests and notifications initiated by the remote server.
+    ///
+    /// Messages from every transport the client connects through are delivered here.
+    /// Returns `None` if the receiver has already been taken.
+    pub async fn take_inbound_receiver(&self) -> Option<InboundReceiver> {
+        self.inbound_rx.lock().await.take()
+    }
+
+    /// Allocates the next id in this client's request sequence.
+    ///
+    /// Requests relayed over the same connection take their ids from here too, so
+    /// they can never collide with the client's own.
+    pub async fn next_request_id(&self) -> u64 {
         let mut id = self.request_id.lock().await;
         *id += 1;
         *id
     }
 
-    async fn try_connect_transport(&self, index: usize) -> Result<Box<dyn McpClientTransport>> {
+    async fn try_connect_transport(&self, index: usize) -> Result<Arc<dyn McpClientTransport>> {
         if index >= self.transports.len() {
             return Err(ClientError::Connection("No more transports to try".to_string()));
         }
 
         let (transport_type, config) = &self.transports[index];
+        let transport_type = match transport_type {
+            TransportType::Http if self.legacy_sse.lock().await.contains(&index) => TransportType::Sse,
+            other => other.clone(),
+        };
         info!("Attempting to connect using {:?} transport", transport_type);
 
-        let mut transport = create_transport(transport_type.clone(), config.clone()).await?;
+        let mut transport = create_transport(transport_type, config.clone()).await?;
+        transport.set_inbound_sender(self.inbound_tx.clone());
         transport.connect().await?;
+        if let Some(version) = self.protocol_version.lock().await.as_ref() {
+            transport.set_protocol_version(&version.to_string()).await;
+        }
 
-        Ok(transport)
+        Ok(Arc::from(transport))
     }
 
     async fn connect_with_fallbacks(&self) -> Result<()> {
@@ -184,65 +215,150 @@ impl McpRemoteClient {
         Err(ClientError::Connection("All transports failed".to_string()))
     }
 
-    async fn ensure_connected(&self) -> Result<()> {
-        let transport_guard = self.current_transport.lock().await;
-        if let Some(transport) = transport_guard.as_ref() {
-            if transport.is_connected().await {
-                return Ok(());
-            }
+    async fn connected_transport(&self) -> Option<Arc<dyn McpClientTransport>> {
+        let transport = self.current_transport.lock().await.clone()?;
+        transport.is_connected().await.then_some(transport)
+    }
+
+    async fn ensure_connected(&self) -> Result<Arc<dyn McpClientTransport>> {
+        if let Some(transport) = self.connected_transport().await {
+            return Ok(transport);
+        }
+
+        // Only one caller reconnects; the others wait and pick up its transport
+        let _connecting = self.connect_lock.lock().await;
+        if let Some(transport) = self.connected_transport().await {
+            return Ok(transport);
         }
-        drop(transport_guard);
 
         // Try to reconnect
-        self.connect_with_fallbacks().await
+        self.connect_with_fallbacks().await?;
+        self.current_transport.lock().await.clone()
+            .ok_or_else(|| ClientError::Connection("No transport available".to_string()))
     }
 
-    async fn send_request_with_retry(&self, request: &str) -> Result<String> {
+    /// Sends a raw JSON-RPC request and returns the raw response.
+    ///
+    /// The transport lock is only held to pick the transport, so any number of
+    /// requests can be in flight at once. On failure the client moves on to the
+    /// next configured transport before retrying.
+    pub async fn send_request_with_retry(&self, request: &str) -> Result<String> {
         const MAX_RETRY_ATTEMPTS: usize = 3;
 
         for attempt in 1..=MAX_RETRY_ATTEMPTS {
-            self.ensure_connected().await?;
-
-            let mut transport_guard = self.current_transport.lock().await;
-            if let Some(transport) = transport_guard.as_mut() {
-                match transport.send_request(request).await {
-                    Ok(response) => return Ok(response),
-                    Err(e) => {
-                        error!("Request attempt {} failed: {}", attempt, e);
-                        if attempt == MAX_RETRY_ATTEMPTS {
-                            return Err(e);
-                        }
-                        // Mark transport as disconnected and try next transport
-                        drop(transport_guard);
-                        *self.current_transport.lock().await = None;
+            let transport = self.ensure_connected().await?;
+
+            match transport.send_request(request).await {
+                Ok(response) => return Ok(response),
+                Err(e) if e.suggests_legacy_sse() && is_initialize(request)
+                    && attempt < MAX_RETRY_ATTEMPTS
+                    && self.fall_back_to_legacy_sse(&transport).await =>
+                {
+                    info!("Server rejected Streamable HTTP initialize ({}), retrying with legacy HTTP+SSE", e);
+                }
+                Err(e) => {
+                    error!("Request attempt {} failed: {}", attempt, e);
+                    if attempt == MAX_RETRY_ATTEMPTS || !e.is_retryable() {
+                        return Err(e);
+                    }
+
+                    // Mark transport as disconnected and try next transport, unless a
+                    // concurrent request has already replaced it
+                    let mut transport_guard = self.current_transport.lock().await;
+                    if transport_guard.as_ref().is_some_and(|current| Arc::ptr_eq(current, &transport)) {
+                        *transport_guard = None;
 
                         // Move to next transport for retry
                         let mut index_guard = self.current_transport_index.lock().await;
                         *index_guard = (*index_guard + 1) % self.transports.len();
                     }
                 }
-            } else {
-                return Err(ClientError::Connection("No transport available".to_string()));
             }
         }
 
         Err(ClientError::Connection("All retry attempts failed".to_string()))
     }
 
-    pub async fn initialize(&self) -> Result<InitializeResult> {
-        let request_id = self.next_request_id().await;
+    /// Sends a JSON-RPC batch and returns the replies to its requests, in no particular order.
+    ///
+    /// Unlike single requests, a failed batch is not retried: the server may
+    /// already have acted on some of its messages.
+    pub async fn send_batch(&self, messages: &[String]) -> Result<Vec<String>> {
+        let transport = self.ensure_connected().await?;
+        transport.send_batch(messages).await
+    }
+
+    /// Switches the current HTTP transport to legacy HTTP+SSE, as the spec advises
+    /// when a server answers the `initialize` POST with 400, 404 or 405.
+    ///
+    /// Returns false if the transport is not HTTP or was already switched.
+    async fn fall_back_to_legacy_sse(&self, transport: &Arc<dyn McpClientTransport>) -> bool {
+        let mut transport_guard = self.current_transport.lock().await;
+        if !transport_guard.as_ref().is_some_and(|current| Arc::ptr_eq(current, transport)) {
+            return false;
+        }
+
+        let index = *self.current_transport_index.lock().await;
+        if !matches!(self.transports[index].0, TransportType::Http)
+            || !self.legacy_sse.lock().await.insert(index)
+        {
+            return false;
+        }
+
+        *transport_guard = None;
+        if let Err(e) = transport.disconnect().await {
+            warn!("Error closing Streamable HTTP transport: {}", e);
+        }
+        true
+    }
+
+    async fn send_message(&self, message: &str) -> Result<()> {
+        let transport = self.ensure_connected().await?;
+        transport.send_message(message).await
+    }
+
+    /// Sends a JSON-RPC notification to the remote server without waiting for a reply.
+    pub async fn send_notification(&self, notification: &str) -> Result<()> {
+        self.send_message(notification).await
+    }
+
+    /// Answers a request the remote server sent through the inbound stream.
+    pub async fn send_response(&self, response: &str) -> Result<()> {
+        self.send_message(response).await
+    }
 
+    pub async fn initialize(&self) -> Result<InitializeResult> {
         let request_params = InitializeRequestParam {
-            protocol_version: ProtocolVersion::default(),
+            protocol_version: self.versions.preferred.clone(),
             capabilities: self.capabilities.clone(),
             client_info: self.client_info.clone(),
         };
 
+        let result = self.initialize_with(serde_json::to_value(request_params)?).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Performs the `initialize` request with caller-supplied params, such as those
+    /// of a client the proxy is relaying for, and returns the server's raw result.
+    ///
+    /// A requested protocol version outside the [`VersionPolicy`] is replaced by
+    /// the preferred one, and a server answering with an unsupported version is
+    /// refused with [`ClientError::UnsupportedProtocolVersion`]. The capabilities the
+    /// server declares are kept for [`Self::server_capabilities`].
+    pub async fn initialize_with(&self, mut params: Value) -> Result<Value> {
+        let request_id = self.next_request_id().await;
+
+        if !protocol_version_of(&params).is_some_and(|version| self.versions.accepts(&version)) {
+            if let Some(params) = params.as_object_mut() {
+                params.insert("protocolVersion".to_string(), serde_json::to_value(&self.versions.preferred)?);
+            }
+        }
+
         let json_request = json!({
             "jsonrpc": "2.0",
             "id": request_id,
             "method": "initialize",
-            "params": request_params
+            "params": params
         });
 
         let request_str = json_request.to_string();
@@ -253,9 +369,10 @@ impl McpRemoteClient {
         if response == "{}" || response.trim().is_empty() {
             // HTTP transport might return empty response for 202 Accepted
             warn!("Received empty response, assuming initialization succeeded");
+            *self.server_capabilities.lock().await = None;
             *self.initialized.lock().await = true;
-            return Ok(InitializeResult {
-                protocol_version: ProtocolVersion::default(),
+            return Ok(serde_json::to_value(InitializeResult {
+                protocol_version: self.versions.preferred.clone(),
                 capabilities: Default::default(),
                 server_info: Implementation {
                     name: "unknown".to_string(),
@@ -265,19 +382,34 @@ impl McpRemoteClient {
                     website_url: None,
                 },
                 instructions: None,
-            });
+            })?);
         }
 
         let parsed: Value = serde_json::from_str(&response)
-            .map_err(|e| ClientError::Json(e))?;
+            .map_err(ClientError::Json)?;
 
         if let Some(error) = parsed.get("error") {
             return Err(ClientError::Protocol(format!("Initialize error: {}", error)));
         }
 
-        let result: InitializeResult = serde_json::from_value(
-            parsed.get("result").unwrap_or(&Value::Null).clone()
-        )?;
+        let result = parsed.get("result").cloned().unwrap_or(Value::Null);
+
+        let version = protocol_version_of(&result)
+            .ok_or_else(|| ClientError::Protocol("Initialize result lacks protocolVersion".to_string()))?;
+        self.versions.check(&version)?;
+        info!("Negotiated protocol version {}", version);
+
+        if let Some(transport) = self.current_transport.lock().await.as_ref() {
+            transport.set_protocol_version(&version.to_string()).await;
+        }
+        *self.protocol_version.lock().await = Some(version);
+
+        let capabilities = serde_json::from_value(result.get("capabilities").cloned().unwrap_or_else(|| json!({})))
+            .unwrap_or_else(|e| {
+                warn!("Ignoring malformed server capabilities: {}", e);
+                ServerCapabilities::default()
+            });
+        *self.server_capabilities.lock().await = Some(capabilities);
 
         *self.initialized.lock().await = true;
         info!("Successfully initialized MCP client");
@@ -286,9 +418,7 @@ impl McpRemoteClient {
     }
 
     pub async fn list_tools(&self) -> Result<Value> {
-        if !*self.initialized.lock().await {
-            return Err(ClientError::Protocol("Client not initialized".to_string()));
-        }
+        self.ensure_ready("tools/list").await?;
 
         let request_id = self.next_request_id().await;
         let request = json!({
@@ -313,9 +443,7 @@ impl McpRemoteClient {
     }
 
     pub async fn list_resources(&self) -> Result<Value> {
-        if !*self.initialized.lock().await {
-            return Err(ClientError::Protocol("Client not initialized".to_string()));
-        }
+        self.ensure_ready("resources/list").await?;
 
         let request_id = self.next_request_id().await;
         let request = json!({
@@ -340,9 +468,7 @@ impl McpRemoteClient {
     }
 
     pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value> {
-        if !*self.initialized.lock().await {
-            return Err(ClientError::Protocol("Client not initialized".to_string()));
-        }
+        self.ensure_ready("tools/call").await?;
 
         let request_id = self.next_request_id().await;
         let request = json!({
@@ -371,9 +497,7 @@ impl McpRemoteClient {
     }
 
     pub async fn read_resource(&self, uri: &str) -> Result<Value> {
-        if !*self.initialized.lock().await {
-            return Err(ClientError::Protocol("Client not initialized".to_string()));
-        }
+        self.ensure_ready("resources/read").await?;
 
         let request_id = self.next_request_id().await;
         let request = json!({
@@ -399,6 +523,187 @@ impl McpRemoteClient {
 
         Ok(parsed.get("result").unwrap_or(&Value::Null).clone())
     }
+
+    /// Fails unless the client is initialized and the server declared the capability `method` needs.
+    async fn ensure_ready(&self, method: &str) -> Result<()> {
+        if !*self.initialized.lock().await {
+            return Err(ClientError::Protocol("Client not initialized".to_string()));
+        }
+        if let Some(capabilities) = self.server_capabilities.lock().await.as_ref() {
+            require_capability(capabilities, method)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a `method` request and deserializes its result.
+    async fn request<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: Option<P>) -> Result<R> {
+        self.ensure_ready(method).await?;
+
+        let request_id = self.next_request_id().await;
+        let mut request = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": method
+        });
+        if let Some(params) = params {
+            request["params"] = serde_json::to_value(params)?;
+        }
+
+        let response = self.send_request_with_retry(&request.to_string()).await?;
+
+        if response == "{}" || response.trim().is_empty() {
+            return Err(ClientError::Protocol(format!("Empty response for {}", method)));
+        }
+
+        let parsed: Value = serde_json::from_str(&response)?;
+
+        if let Some(error) = parsed.get("error") {
+            return Err(ClientError::Protocol(format!("{} error: {}", method, error)));
+        }
+
+        Ok(serde_json::from_value(parsed.get("result").cloned().unwrap_or(Value::Null))?)
+    }
+
+    /// Requests the page of a list result starting at `cursor`.
+    async fn page<P: Page>(&self, cursor: Option<String>) -> Result<P> {
+        let params = cursor.map(|cursor| PaginatedRequestParam { cursor: Some(cursor) });
+        self.request(P::METHOD, params).await
+    }
+
+    /// Streams the items of a list result, following `nextCursor` from page to page.
+    ///
+    /// Ends with an error if the server has more than [`DEFAULT_MAX_PAGES`] pages,
+    /// so a server that never stops handing out cursors cannot loop forever.
+    fn paginate<P: Page>(&self) -> impl Stream<Item = Result<P::Item>> + Send + '_ {
+        // The cursor is `None` once the last page has been fetched
+        let start: (Option<Option<String>>, usize) = (Some(None), 0);
+        stream::try_unfold(start, move |(cursor, pages)| async move {
+            let Some(cursor) = cursor else {
+                return Ok::<_, ClientError>(None);
+            };
+            if pages == DEFAULT_MAX_PAGES {
+                return Err(too_many_pages(P::METHOD, DEFAULT_MAX_PAGES));
+            }
+            let (items, next_cursor) = self.page::<P>(cursor).await?.into_parts();
+            Ok(Some((stream::iter(items.into_iter().map(Ok)), (next_cursor.map(Some), pages + 1))))
+        })
+        .try_flatten()
+    }
+
+    /// Collects the items of a list result, failing if the server has more than `max_pages` pages.
+    async fn collect_pages<P: Page>(&self, max_pages: usize) -> Result<Vec<P::Item>> {
+        if max_pages == 0 {
+            return Err(ClientError::Protocol(format!("Cannot list {} in 0 pages", P::METHOD)));
+        }
+
+        let mut items = Vec::new();
+        let mut cursor = None;
+        for _ in 0..max_pages {
+            let (page, next_cursor) = self.page::<P>(cursor).await?.into_parts();
+            items.extend(page);
+            match next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => return Ok(items),
+            }
+        }
+
+        Err(too_many_pages(P::METHOD, max_pages))
+    }
+
+    /// Lists one page of tools, starting at `cursor`.
+    pub async fn list_tools_page(&self, cursor: Option<String>) -> Result<ListToolsResult> {
+        self.page(cursor).await
+    }
+
+    /// Streams every tool, across at most [`DEFAULT_MAX_PAGES`] pages.
+    pub fn tools(&self) -> impl Stream<Item = Result<Tool>> + Send + '_ {
+        self.paginate::<ListToolsResult>()
+    }
+
+    /// Collects every tool, reading at most `max_pages` pages.
+    pub async fn list_all_tools(&self, max_pages: usize) -> Result<Vec<Tool>> {
+        self.collect_pages::<ListToolsResult>(max_pages).await
+    }
+
+    /// Lists one page of resources, starting at `cursor`.
+    pub async fn list_resources_page(&self, cursor: Option<String>) -> Result<ListResourcesResult> {
+        self.page(cursor).await
+    }
+
+    /// Streams every resource, across at most [`DEFAULT_MAX_PAGES`] pages.
+    pub fn resources(&self) -> impl Stream<Item = Result<Resource>> + Send + '_ {
+        self.paginate::<ListResourcesResult>()
+    }
+
+    /// Collects every resource, reading at most `max_pages` pages.
+    pub async fn list_all_resources(&self, max_pages: usize) -> Result<Vec<Resource>> {
+        self.collect_pages::<ListResourcesResult>(max_pages).await
+    }
+
+    /// Lists one page of prompts, starting at `cursor`.
+    pub async fn list_prompts(&self, cursor: Option<String>) -> Result<ListPromptsResult> {
+        self.page(cursor).await
+    }
+
+    pub async fn get_prompt(&self, params: GetPromptRequestParam) -> Result<GetPromptResult> {
+        self.request("prompts/get", Some(params)).await
+    }
+
+    /// Lists one page of resource templates, starting at `cursor`.
+    pub async fn list_resource_templates(&self, cursor: Option<String>) -> Result<ListResourceTemplatesResult> {
+        self.page(cursor).await
+    }
+
+    /// Asks the server to send `notifications/resources/updated` when `uri` changes.
+    pub async fn subscribe(&self, uri: &str) -> Result<()> {
+        let params = SubscribeRequestParam { uri: uri.to_string() };
+        let _: EmptyResult = self.request("resources/subscribe", Some(params)).await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe(&self, uri: &str) -> Result<()> {
+        let params = UnsubscribeRequestParam { uri: uri.to_string() };
+        let _: EmptyResult = self.request("resources/unsubscribe", Some(params)).await?;
+        Ok(())
+    }
+
+    /// Requests completions for a prompt or resource template argument.
+    pub async fn complete(&self, params: CompleteRequestParam) -> Result<CompleteResult> {
+        self.request("completion/complete", Some(params)).await
+    }
+
+    /// Sets the minimum level of the `notifications/message` the server sends.
+    pub async fn set_logging_level(&self, level: LoggingLevel) -> Result<()> {
+        let _: EmptyResult = self.request("logging/setLevel", Some(SetLevelRequestParam { level })).await?;
+        Ok(())
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        let _: EmptyResult = self.request("ping", None::<()>).await?;
+        Ok(())
+    }
+}
+
+/// Maps an `http(s)://` endpoint to the matching `ws(s)://` URL.
+fn websocket_url(endpoint: &str) -> String {
+    if let Some(rest) = endpoint.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = endpoint.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        endpoint.to_string()
+    }
+}
+
+fn too_many_pages(method: &str, max_pages: usize) -> ClientError {
+    ClientError::Protocol(format!("{} returned more than {} pages", method, max_pages))
+}
+
+fn is_initialize(request: &str) -> bool {
+    serde_json::from_str::<Value>(request)
+        .ok()
+        .and_then(|v| v.get("method").and_then(|m| m.as_str()).map(|m| m == "initialize"))
+        .unwrap_or(false)
 }
 
 #[async_trait::async_trait]
@@ -414,7 +719,7 @@ impl McpClient for McpRemoteClient {
     }
 
     async fn disconnect(&mut self) -> mcp_types::Result<()> {
-        if let Some(mut transport) = self.current_transport.lock().await.take() {
+        if let Some(transport) = self.current_transport.lock().await.take() {
             transport.disconnect().await
                 .map_err(|e| mcp_types::McpError::Transport(e.to_string()))?;
         }
@@ -422,3 +727,267 @@ impl McpClient for McpRemoteClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::legacy_sse::tests::LegacyServer;
+    use futures::StreamExt;
+    use rmcp::model::{ArgumentInfo, Reference};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Serves one connection, declaring `capabilities` and answering each request by method.
+    async fn serve(capabilities: Value) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let Some(id) = request.get("id").cloned() else { continue };
+                let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+                let reply = match request["method"].as_str().unwrap() {
+                    "initialize" => json!({"result": {
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": capabilities,
+                        "serverInfo": {"name": "mock", "version": "1.0"}
+                    }}),
+                    "prompts/list" => json!({"result": {
+                        "prompts": [{"name": "review", "arguments": [{"name": "code", "required": true}]}],
+                        "nextCursor": params["cursor"]
+                    }}),
+                    "prompts/get" => json!({"result": {
+                        "messages": [{"role": "user", "content": {"type": "text", "text": params["arguments"]["code"]}}]
+                    }}),
+                    "completion/complete" => json!({"result": {
+                        "completion": {"values": [format!("{}rust", params["argument"]["value"].as_str().unwrap())]}
+                    }}),
+                    "tools/list" if params["cursor"].is_null() => json!({"result": {
+                        "tools": [{"name": "search", "inputSchema": {}}, {"name": "fetch", "inputSchema": {}}],
+                        "nextCursor": "2"
+                    }}),
+                    "tools/list" => json!({"result": {"tools": [{"name": "summarize", "inputSchema": {}}]}}),
+                    // A server that never runs out of pages
+                    "resources/list" => json!({"result": {
+                        "resources": [{"uri": "file:///a.md", "name": "a"}],
+                        "nextCursor": "more"
+                    }}),
+                    "resources/templates/list" => json!({"result": {
+                        "resourceTemplates": [{"uriTemplate": "file:///{path}", "name": "files"}]
+                    }}),
+                    "resources/subscribe" if params["uri"] == "file:///missing" => {
+                        json!({"error": {"code": -32002, "message": "Resource not found"}})
+                    }
+                    "ping" | "logging/setLevel" | "resources/subscribe" | "resources/unsubscribe" => {
+                        json!({"result": {}})
+                    }
+                    _ => json!({"error": {"code": -32601, "message": "Method not found"}}),
+                };
+
+                let mut reply = reply;
+                reply["jsonrpc"] = json!("2.0");
+                reply["id"] = id;
+                writer.write_all(format!("{}\n", reply).as_bytes()).await.unwrap();
+            }
+        });
+
+        address
+    }
+
+    fn all_capabilities() -> Value {
+        json!({
+            "tools": {},
+            "resources": {"subscribe": true},
+            "prompts": {},
+            "completions": {},
+            "logging": {}
+        })
+    }
+
+    #[tokio::test]
+    async fn test_typed_requests() {
+        let config = TransportConfig { endpoint: serve(all_capabilities()).await, retry_attempts: 1, ..Default::default() };
+        let client = McpRemoteClient::with_custom_transports(vec![(TransportType::Tcp, config)]).await;
+
+        assert!(client.ping().await.is_err(), "requests before initialize must fail");
+        client.initialize().await.unwrap();
+        client.ping().await.unwrap();
+
+        let prompts = client.list_prompts(Some("page-2".to_string())).await.unwrap();
+        assert_eq!(prompts.prompts[0].name, "review");
+        assert_eq!(prompts.next_cursor.as_deref(), Some("page-2"));
+        assert_eq!(client.list_prompts(None).await.unwrap().next_cursor, None);
+
+        let mut arguments = serde_json::Map::new();
+        arguments.insert("code".to_string(), json!("fn main() {}"));
+        let prompt = client
+            .get_prompt(GetPromptRequestParam { name: "review".to_string(), arguments: Some(arguments) })
+            .await
+            .unwrap();
+        assert_eq!(prompt.messages.len(), 1);
+
+        let completion = client
+            .complete(CompleteRequestParam {
+                r#ref: Reference::for_prompt("review"),
+                argument: ArgumentInfo { name: "language".to_string(), value: "ru".to_string() },
+                context: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(completion.completion.values, vec!["rurust".to_string()]);
+
+        let templates = client.list_resource_templates(None).await.unwrap();
+        assert_eq!(templates.resource_templates[0].uri_template, "file:///{path}");
+
+        client.set_logging_level(LoggingLevel::Warning).await.unwrap();
+        client.subscribe("file:///notes.md").await.unwrap();
+        client.unsubscribe("file:///notes.md").await.unwrap();
+
+        let error = client.subscribe("file:///missing").await.unwrap_err();
+        assert!(error.to_string().contains("resources/subscribe error"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let config = TransportConfig { endpoint: serve(all_capabilities()).await, retry_attempts: 1, ..Default::default() };
+        let client = McpRemoteClient::with_custom_transports(vec![(TransportType::Tcp, config)]).await;
+        client.initialize().await.unwrap();
+
+        let first = client.list_tools_page(None).await.unwrap();
+        assert_eq!(first.next_cursor.as_deref(), Some("2"));
+
+        let tools: Vec<Tool> = client.tools().map(|tool| tool.unwrap()).collect().await;
+        let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(names, ["search", "fetch", "summarize"]);
+        assert_eq!(client.list_all_tools(2).await.unwrap().len(), 3);
+
+        let resources: Vec<Result<Resource>> = client.resources().take(5).collect().await;
+        assert_eq!(resources.len(), 5);
+        let error = client.list_all_resources(3).await.unwrap_err();
+        assert_eq!(error.to_string(), "Protocol error: resources/list returned more than 3 pages");
+        assert!(client.list_all_resources(0).await.is_err());
+
+        // Streaming stops at the page cap too
+        let resources: Vec<Result<Resource>> = client.resources().collect().await;
+        assert_eq!(resources.len(), DEFAULT_MAX_PAGES + 1);
+        let error = resources.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(error.to_string(), format!("Protocol error: resources/list returned more than {} pages", DEFAULT_MAX_PAGES));
+    }
+
+    #[tokio::test]
+    async fn test_undeclared_capabilities_fail_fast() {
+        let config = TransportConfig { endpoint: serve(json!({"tools": {}})).await, retry_attempts: 1, ..Default::default() };
+        let client = McpRemoteClient::with_custom_transports(vec![(TransportType::Tcp, config)]).await;
+
+        assert_eq!(client.server_capabilities().await, None);
+        client.initialize().await.unwrap();
+        assert!(client.server_capabilities().await.unwrap().tools.is_some());
+
+        // The mock would answer these; the client must not ask
+        assert!(matches!(
+            client.list_prompts(None).await,
+            Err(ClientError::CapabilityNotSupported { capability, .. }) if capability == "prompts"
+        ));
+        assert!(matches!(client.list_all_resources(1).await, Err(ClientError::CapabilityNotSupported { .. })));
+        assert!(matches!(client.set_logging_level(LoggingLevel::Info).await, Err(ClientError::CapabilityNotSupported { .. })));
+        assert!(matches!(client.list_resources().await, Err(ClientError::CapabilityNotSupported { .. })));
+        assert!(matches!(
+            client.read_resource("file:///a.md").await,
+            Err(ClientError::CapabilityNotSupported { capability, .. }) if capability == "resources"
+        ));
+        assert_eq!(client.list_tools().await.unwrap()["tools"].as_array().map(Vec::len), Some(2));
+
+        assert_eq!(client.list_all_tools(2).await.unwrap().len(), 3);
+        client.ping().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_tool_call_is_not_resent() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let (calls_tx, mut calls) = tokio::sync::mpsc::unbounded_channel();
+
+        // Answers initialize on every connection but never a tool call
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let calls_tx = calls_tx.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let request: Value = serde_json::from_str(&line).unwrap();
+                        match request["method"].as_str() {
+                            Some("initialize") => {
+                                let reply = json!({"jsonrpc": "2.0", "id": request["id"], "result": {
+                                    "protocolVersion": "2025-03-26",
+                                    "capabilities": {"tools": {}},
+                                    "serverInfo": {"name": "mock", "version": "1.0"}
+                                }});
+                                writer.write_all(format!("{}\n", reply).as_bytes()).await.unwrap();
+                            }
+                            Some("tools/call") => calls_tx.send(request).unwrap(),
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+
+        let config = TransportConfig {
+            endpoint,
+            timeout: std::time::Duration::from_millis(200),
+            retry_attempts: 3,
+            retry_delay: std::time::Duration::from_millis(10),
+            ..Default::default()
+        };
+        let client = McpRemoteClient::with_custom_transports(vec![(TransportType::Tcp, config)]).await;
+        client.initialize().await.unwrap();
+
+        let error = client.call_tool("charge_card", json!({"amount": 10})).await.unwrap_err();
+        assert!(matches!(error, ClientError::RequestInterrupted(_)), "{}", error);
+
+        assert_eq!(calls.recv().await.unwrap()["params"]["name"], "charge_card");
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        assert!(calls.try_recv().is_err(), "the tool call was sent again");
+    }
+
+    async fn legacy_client(server: &LegacyServer) -> McpRemoteClient {
+        let config = TransportConfig {
+            endpoint: server.endpoint.clone(),
+            retry_attempts: 1,
+            retry_delay: std::time::Duration::from_millis(10),
+            ..Default::default()
+        };
+        McpRemoteClient::new_with_config(config, vec![])
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_legacy_sse() {
+        let server = LegacyServer::start("404 Not Found").await;
+        let client = legacy_client(&server).await;
+
+        let result = client.initialize().await.unwrap();
+        assert_eq!(result.server_info.name, "legacy");
+        assert_eq!(client.protocol_version().await, Some(ProtocolVersion::V_2024_11_05));
+    }
+
+    #[tokio::test]
+    async fn test_auth_errors_do_not_fall_back() {
+        let mut server = LegacyServer::start("401 Unauthorized").await;
+        let client = legacy_client(&server).await;
+
+        let error = client.initialize().await.unwrap_err();
+        assert!(error.is_http_client_error(), "{}", error);
+
+        // Nothing was ever posted the legacy way
+        while let Ok(request) = server.requests.try_recv() {
+            assert!(!request.starts_with("POST /messages"), "{}", request);
+        }
+    }
+}
@@ -8,6 +8,10 @@ pub enum ClientError {
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// The server answered with a non-success HTTP status.
+    #[error("HTTP error: {0}")]
+    HttpStatus(reqwest::StatusCode),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -26,11 +30,56 @@ pub enum ClientError {
     #[error("Timeout")]
     Timeout,
 
+    #[error("TLS error: {0}")]
+    Tls(String),
+
     #[error("MCP error: {0}")]
     Mcp(#[from] mcp_types::McpError),
 
     #[error("OAuth error: {0}")]
     OAuthError(String),
+
+    /// The server accepted the request but its response could not be recovered.
+    #[error("Request interrupted: {0}")]
+    RequestInterrupted(String),
+
+    /// The server chose a protocol version the client does not support.
+    #[error("Unsupported protocol version {server}: client supports {supported}")]
+    UnsupportedProtocolVersion { server: String, supported: String },
+
+    /// The server did not declare the capability a request needs in `initialize`.
+    #[error("Server does not support {method}: no {capability} capability")]
+    CapabilityNotSupported { method: String, capability: String },
+}
+
+impl ClientError {
+    /// Whether re-sending the request is safe.
+    ///
+    /// A request that was written to the server must not be re-sent, since a
+    /// non-idempotent tool call would run twice. Transports therefore report a
+    /// timeout or lost connection after the send as [`ClientError::RequestInterrupted`];
+    /// failures before it, such as connect errors, stay retryable.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, ClientError::RequestInterrupted(_))
+    }
+
+    /// Whether the server rejected the request with a 4xx status.
+    pub fn is_http_client_error(&self) -> bool {
+        matches!(self, ClientError::HttpStatus(status) if status.is_client_error())
+    }
+
+    /// Whether the server rejected a Streamable HTTP POST the way a legacy
+    /// HTTP+SSE server does: 404 or 405, or 400 from older servers.
+    ///
+    /// Other 4xx statuses, such as 401 and 403, are real errors.
+    pub fn suggests_legacy_sse(&self) -> bool {
+        matches!(
+            self,
+            ClientError::HttpStatus(
+                reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::METHOD_NOT_ALLOWED
+            )
+        )
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ClientError>;
@@ -2,8 +2,13 @@ pub mod client;
 pub mod transport;
 pub mod error;
 pub mod auth;
+pub mod protocol;
+pub mod pagination;
 
 pub use client::McpRemoteClient;
 pub use error::ClientError;
-pub use transport::{HttpTransport, StdioTransport, TcpTransport};
+pub use transport::{HttpTransport, InboundReceiver, ProxyConfig, SseTransport, StdioTransport, TcpTransport, WebSocketTransport};
+#[cfg(unix)]
+pub use transport::UnixTransport;
 pub use auth::{OAuthClient, OAuthClientConfig, ClientToken};
+pub use protocol::{Shims, VersionPolicy};
@@ -0,0 +1,44 @@
+//! Cursor-based pagination of list results.
+//!
+//! Servers may split `tools/list`, `resources/list` and the other list results
+//! into pages, handing back a `nextCursor` to pass in the next request. A
+//! missing cursor marks the last page.
+
+use rmcp::model::{
+    ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult, Prompt, Resource,
+    ResourceTemplate, Tool,
+};
+use serde::de::DeserializeOwned;
+
+/// The page cap for streamed lists, also used by the CLI when it collects a whole list.
+pub const DEFAULT_MAX_PAGES: usize = 100;
+
+/// One page of a paginated list result.
+pub trait Page: DeserializeOwned + Send {
+    type Item: Send;
+
+    /// The request method returning this page.
+    const METHOD: &'static str;
+
+    /// Splits the page into its items and the cursor of the next page.
+    fn into_parts(self) -> (Vec<Self::Item>, Option<String>);
+}
+
+macro_rules! page {
+    ($result:ty, $item:ty, $method:literal, $items:ident) => {
+        impl Page for $result {
+            type Item = $item;
+
+            const METHOD: &'static str = $method;
+
+            fn into_parts(self) -> (Vec<Self::Item>, Option<String>) {
+                (self.$items, self.next_cursor)
+            }
+        }
+    };
+}
+
+page!(ListToolsResult, Tool, "tools/list", tools);
+page!(ListResourcesResult, Resource, "resources/list", resources);
+page!(ListResourceTemplatesResult, ResourceTemplate, "resources/templates/list", resource_templates);
+page!(ListPromptsResult, Prompt, "prompts/list", prompts);
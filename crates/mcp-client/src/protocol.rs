@@ -0,0 +1,235 @@
+//! Protocol version negotiation, and the shims that let a client and a server
+//! on different protocol revisions talk through the proxy.
+//!
+//! The client offers its preferred version in `initialize` and refuses any
+//! version the server answers with that it does not support. When the proxy
+//! relays for a local client on an older revision than the server, [`Shims`]
+//! downgrade the features that revision predates.
+
+use crate::error::{ClientError, Result};
+use rmcp::model::ServerCapabilities;
+use serde_json::{json, Value};
+
+pub use rmcp::model::ProtocolVersion;
+
+/// The protocol versions a client offers and accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionPolicy {
+    /// Offered in `initialize`
+    pub preferred: ProtocolVersion,
+    /// Accepted from the server, in addition to the preferred version
+    pub supported: Vec<ProtocolVersion>,
+}
+
+impl Default for VersionPolicy {
+    fn default() -> Self {
+        Self {
+            preferred: ProtocolVersion::LATEST,
+            supported: vec![
+                ProtocolVersion::V_2025_06_18,
+                ProtocolVersion::V_2025_03_26,
+                ProtocolVersion::V_2024_11_05,
+            ],
+        }
+    }
+}
+
+impl VersionPolicy {
+    /// Offers `preferred` and accepts every version this crate knows.
+    pub fn new(preferred: ProtocolVersion) -> Self {
+        Self { preferred, ..Default::default() }
+    }
+
+    pub fn with_supported(mut self, supported: Vec<ProtocolVersion>) -> Self {
+        self.supported = supported;
+        self
+    }
+
+    pub fn accepts(&self, version: &ProtocolVersion) -> bool {
+        *version == self.preferred || self.supported.contains(version)
+    }
+
+    /// Checks the version the server chose.
+    pub fn check(&self, version: &ProtocolVersion) -> Result<()> {
+        if self.accepts(version) {
+            return Ok(());
+        }
+
+        let others = self.supported.iter().filter(|v| **v != self.preferred);
+        let supported: Vec<String> = std::iter::once(&self.preferred).chain(others).map(ToString::to_string).collect();
+        Err(ClientError::UnsupportedProtocolVersion {
+            server: version.to_string(),
+            supported: supported.join(", "),
+        })
+    }
+}
+
+/// Parses a `YYYY-MM-DD` protocol version.
+pub fn parse_protocol_version(version: &str) -> Result<ProtocolVersion> {
+    let well_formed = version.len() == 10
+        && version.char_indices().all(|(i, c)| if i == 4 || i == 7 { c == '-' } else { c.is_ascii_digit() });
+    if !well_formed {
+        return Err(ClientError::Protocol(format!(
+            "Invalid protocol version '{}': expected YYYY-MM-DD",
+            version
+        )));
+    }
+    Ok(serde_json::from_value(Value::String(version.to_string()))?)
+}
+
+/// Reads the `protocolVersion` of `initialize` params or an `InitializeResult`.
+pub fn protocol_version_of(message: &Value) -> Option<ProtocolVersion> {
+    serde_json::from_value(message.get("protocolVersion")?.clone()).ok()
+}
+
+/// Checks that the server declared the capability `method` depends on.
+pub fn require_capability(capabilities: &ServerCapabilities, method: &str) -> Result<()> {
+    let (capability, declared) = match method {
+        "resources/subscribe" | "resources/unsubscribe" => (
+            "resources.subscribe",
+            capabilities.resources.as_ref().is_some_and(|resources| resources.subscribe == Some(true)),
+        ),
+        "completion/complete" => ("completions", capabilities.completions.is_some()),
+        "logging/setLevel" => ("logging", capabilities.logging.is_some()),
+        _ if method.starts_with("tools/") => ("tools", capabilities.tools.is_some()),
+        _ if method.starts_with("resources/") => ("resources", capabilities.resources.is_some()),
+        _ if method.starts_with("prompts/") => ("prompts", capabilities.prompts.is_some()),
+        _ => return Ok(()),
+    };
+
+    if declared {
+        return Ok(());
+    }
+    Err(ClientError::CapabilityNotSupported {
+        method: method.to_string(),
+        capability: capability.to_string(),
+    })
+}
+
+/// Translates between the revision the local client speaks and the one the server negotiated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shims {
+    client: ProtocolVersion,
+    server: ProtocolVersion,
+}
+
+impl Shims {
+    pub fn new(client: ProtocolVersion, server: ProtocolVersion) -> Self {
+        Self { client, server }
+    }
+
+    /// Whether the client predates a feature the server's revision introduced in `since`.
+    fn client_lacks(&self, since: &ProtocolVersion) -> bool {
+        self.client < *since && self.server >= *since
+    }
+
+    /// Adapts the server's reply to a `method` request for the client.
+    ///
+    /// Clients before 2025-06-18 know neither `structuredContent` in tool results
+    /// nor `outputSchema` on tools: the structured result becomes a text block,
+    /// unless the server already provided unstructured content, and schemas are
+    /// dropped.
+    pub fn adapt_response(&self, method: &str, response: &str) -> String {
+        if !self.client_lacks(&ProtocolVersion::V_2025_06_18) {
+            return response.to_string();
+        }
+        let Ok(mut parsed) = serde_json::from_str::<Value>(response) else {
+            return response.to_string();
+        };
+        let Some(result) = parsed.get_mut("result").and_then(Value::as_object_mut) else {
+            return response.to_string();
+        };
+
+        match method {
+            "tools/call" => {
+                let Some(structured) = result.remove("structuredContent") else {
+                    return response.to_string();
+                };
+                let content = result.entry("content").or_insert_with(|| json!([]));
+                if content.as_array().is_some_and(Vec::is_empty) {
+                    *content = json!([{"type": "text", "text": structured.to_string()}]);
+                }
+            }
+            "tools/list" => {
+                for tool in result.get_mut("tools").and_then(Value::as_array_mut).into_iter().flatten() {
+                    if let Some(tool) = tool.as_object_mut() {
+                        tool.remove("outputSchema");
+                    }
+                }
+            }
+            _ => return response.to_string(),
+        }
+        parsed.to_string()
+    }
+
+    /// The error to answer a server request with when the client's revision
+    /// predates it, such as elicitation before 2025-06-18. Such requests are not
+    /// forwarded to the client.
+    pub fn reject_server_request(&self, request: &str) -> Option<String> {
+        let parsed = serde_json::from_str::<Value>(request).ok()?;
+        let method = parsed.get("method")?.as_str()?;
+        if method != "elicitation/create" || !self.client_lacks(&ProtocolVersion::V_2025_06_18) {
+            return None;
+        }
+
+        Some(json!({
+            "jsonrpc": "2.0",
+            "id": parsed.get("id")?,
+            "error": {
+                "code": -32601,
+                "message": format!("Method not found: the client speaks protocol version {}, which has no {}", self.client, method)
+            }
+        }).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_policy() {
+        let policy = VersionPolicy::new(ProtocolVersion::V_2025_06_18)
+            .with_supported(vec![ProtocolVersion::V_2025_03_26]);
+
+        assert!(policy.check(&ProtocolVersion::V_2025_06_18).is_ok());
+        assert!(policy.check(&ProtocolVersion::V_2025_03_26).is_ok());
+        let error = policy.check(&ProtocolVersion::V_2024_11_05).unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported protocol version 2024-11-05: client supports 2025-06-18, 2025-03-26");
+
+        assert_eq!(parse_protocol_version("2024-11-05").unwrap(), ProtocolVersion::V_2024_11_05);
+        assert!(parse_protocol_version("latest").is_err());
+    }
+
+    #[test]
+    fn test_require_capability() {
+        let capabilities: ServerCapabilities =
+            serde_json::from_value(json!({"tools": {}, "resources": {"listChanged": true}})).unwrap();
+
+        assert!(require_capability(&capabilities, "tools/call").is_ok());
+        assert!(require_capability(&capabilities, "resources/read").is_ok());
+        assert!(require_capability(&capabilities, "ping").is_ok());
+        assert!(require_capability(&capabilities, "prompts/list").is_err());
+
+        let error = require_capability(&capabilities, "resources/subscribe").unwrap_err();
+        assert_eq!(error.to_string(), "Server does not support resources/subscribe: no resources.subscribe capability");
+    }
+
+    #[test]
+    fn test_shims_downgrade_structured_output_and_elicitation() {
+        let shims = Shims::new(ProtocolVersion::V_2024_11_05, ProtocolVersion::V_2025_06_18);
+
+        let result = shims.adapt_response("tools/call", r#"{"jsonrpc":"2.0","id":1,"result":{"content":[],"structuredContent":{"temp":21}}}"#);
+        let result: Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(result["result"], json!({"content": [{"type": "text", "text": r#"{"temp":21}"#}]}));
+
+        let tools = shims.adapt_response("tools/list", r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"w","outputSchema":{}}]}}"#);
+        assert!(!tools.contains("outputSchema"));
+
+        let elicitation = r#"{"jsonrpc":"2.0","id":"e1","method":"elicitation/create","params":{}}"#;
+        assert!(shims.reject_server_request(elicitation).unwrap().contains("-32601"));
+
+        let current = Shims::new(ProtocolVersion::V_2025_06_18, ProtocolVersion::V_2025_06_18);
+        assert_eq!(current.reject_server_request(elicitation), None);
+    }
+}
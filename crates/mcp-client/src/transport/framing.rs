@@ -0,0 +1,297 @@
+//! How JSON-RPC messages are delimited on byte streams.
+//!
+//! The stdio, TCP and Unix socket transports all carry a sequence of messages
+//! over one stream. Most servers separate them with newlines, but some speak
+//! LSP-style `Content-Length:` headers or a binary length prefix instead.
+
+use crate::error::ClientError;
+use serde::de::IgnoredAny;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// Largest message accepted from the peer, so a corrupt length cannot exhaust memory.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// The framing used on a stream transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// One message per line. Pretty-printed messages spanning several lines are
+    /// reassembled on read.
+    #[default]
+    Ndjson,
+    /// `Content-Length: <n>` headers and a blank line before each message, as in LSP
+    ContentLength,
+    /// A 4-byte big-endian length before each message
+    LengthPrefixed,
+}
+
+impl FromStr for Framing {
+    type Err = ClientError;
+
+    fn from_str(framing: &str) -> crate::error::Result<Self> {
+        match framing.to_lowercase().as_str() {
+            "ndjson" | "newline" | "jsonl" => Ok(Framing::Ndjson),
+            "content-length" | "lsp" => Ok(Framing::ContentLength),
+            "length-prefixed" | "length" => Ok(Framing::LengthPrefixed),
+            _ => Err(ClientError::Connection(format!(
+                "Unknown framing '{}': expected ndjson, content-length or length-prefixed",
+                framing
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for Framing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Framing::Ndjson => write!(f, "ndjson"),
+            Framing::ContentLength => write!(f, "content-length"),
+            Framing::LengthPrefixed => write!(f, "length-prefixed"),
+        }
+    }
+}
+
+impl Framing {
+    /// Encodes one message, delimiter included.
+    pub fn encode(self, message: &str) -> Vec<u8> {
+        match self {
+            Framing::Ndjson => {
+                // Raw line breaks in JSON can only be whitespace between tokens, so a
+                // pretty-printed message relayed from a framed peer still fits on one line
+                let mut frame: Vec<u8> = message.bytes().filter(|b| !matches!(b, b'\n' | b'\r')).collect();
+                frame.push(b'\n');
+                frame
+            }
+            Framing::ContentLength => {
+                let mut frame = format!("Content-Length: {}\r\n\r\n", message.len()).into_bytes();
+                frame.extend_from_slice(message.as_bytes());
+                frame
+            }
+            Framing::LengthPrefixed => {
+                let mut frame = Vec::with_capacity(message.len() + 4);
+                frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+                frame.extend_from_slice(message.as_bytes());
+                frame
+            }
+        }
+    }
+
+    /// Writes one message and flushes it.
+    pub async fn write_frame<W>(self, writer: &mut W, message: &str) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        writer.write_all(&self.encode(message)).await?;
+        writer.flush().await
+    }
+
+    /// Reads the next message, or `None` once the stream ends between messages.
+    pub async fn read_frame<R>(self, reader: &mut R) -> io::Result<Option<String>>
+    where
+        R: AsyncBufRead + Unpin + ?Sized,
+    {
+        match self {
+            Framing::Ndjson => read_ndjson(reader).await,
+            Framing::ContentLength => read_content_length(reader).await,
+            Framing::LengthPrefixed => read_length_prefixed(reader).await,
+        }
+    }
+}
+
+/// Writes one message to a shared writer on a task of its own.
+///
+/// A caller that is cancelled mid-write, such as a request the client gave up
+/// on, would otherwise leave half a frame on the stream and corrupt every
+/// message after it.
+pub(crate) async fn write_detached<W>(
+    writer: &Arc<Mutex<Option<W>>>,
+    framing: Framing,
+    message: &str,
+    missing: &'static str,
+) -> crate::error::Result<()>
+where
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let writer = writer.clone();
+    let frame = framing.encode(message);
+
+    tokio::spawn(async move {
+        let mut writer_guard = writer.lock().await;
+        let writer = writer_guard.as_mut()
+            .ok_or_else(|| ClientError::Connection(missing.to_string()))?;
+
+        writer.write_all(&frame).await?;
+        writer.flush().await?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| ClientError::Transport(format!("Write task failed: {}", e)))?
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Collects lines until they form a complete JSON value.
+async fn read_ndjson<R>(reader: &mut R) -> io::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut buffer = Vec::new();
+    loop {
+        if reader.read_until(b'\n', &mut buffer).await? == 0 {
+            if buffer.iter().all(u8::is_ascii_whitespace) {
+                return Ok(None);
+            }
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended inside a message"));
+        }
+        if buffer.len() > MAX_FRAME_SIZE {
+            return Err(invalid_data(format!("message exceeds {} bytes", MAX_FRAME_SIZE)));
+        }
+
+        // Blank lines between messages carry nothing
+        if buffer.iter().all(u8::is_ascii_whitespace) {
+            buffer.clear();
+            continue;
+        }
+
+        // An unterminated value continues on the next line; anything else, even
+        // malformed JSON, is handed on for the caller to reject
+        match serde_json::from_slice::<IgnoredAny>(&buffer) {
+            Err(e) if e.is_eof() => continue,
+            _ => return Ok(Some(String::from_utf8_lossy(&buffer).trim().to_string())),
+        }
+    }
+}
+
+async fn read_content_length<R>(reader: &mut R) -> io::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    let mut content_length = None;
+    let mut saw_header = false;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).await? == 0 {
+            if saw_header {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended inside a header"));
+            }
+            return Ok(None);
+        }
+
+        let header = String::from_utf8_lossy(&line);
+        let header = header.trim();
+        if header.is_empty() {
+            if saw_header {
+                break;
+            }
+            continue; // Stray blank lines between messages
+        }
+        saw_header = true;
+
+        // Other headers, such as Content-Type, are allowed and ignored
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse::<usize>()
+                    .map_err(|e| invalid_data(format!("invalid Content-Length '{}': {}", value.trim(), e)))?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let length = content_length.ok_or_else(|| invalid_data("message headers lack Content-Length".to_string()))?;
+    read_body(reader, length).await.map(Some)
+}
+
+async fn read_length_prefixed<R>(reader: &mut R) -> io::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    if reader.fill_buf().await?.is_empty() {
+        return Ok(None);
+    }
+
+    let mut prefix = [0u8; 4];
+    reader.read_exact(&mut prefix).await?;
+    read_body(reader, u32::from_be_bytes(prefix) as usize).await.map(Some)
+}
+
+async fn read_body<R>(reader: &mut R, length: usize) -> io::Result<String>
+where
+    R: AsyncBufRead + Unpin + ?Sized,
+{
+    if length > MAX_FRAME_SIZE {
+        return Err(invalid_data(format!("message of {} bytes exceeds {} bytes", length, MAX_FRAME_SIZE)));
+    }
+
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    async fn read_all(framing: Framing, bytes: &[u8]) -> Vec<String> {
+        let mut reader = BufReader::new(bytes);
+        let mut messages = Vec::new();
+        while let Some(message) = framing.read_frame(&mut reader).await.unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let messages = [r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#, r#"{"jsonrpc":"2.0","method":"a/b"}"#];
+
+        for framing in [Framing::Ndjson, Framing::ContentLength, Framing::LengthPrefixed] {
+            let mut stream = Vec::new();
+            for message in messages {
+                framing.write_frame(&mut stream, message).await.unwrap();
+            }
+            assert_eq!(read_all(framing, &stream).await, messages, "{}", framing);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_reassembles_pretty_printed_messages() {
+        let stream = b"{\n  \"jsonrpc\": \"2.0\",\n  \"params\": {\"text\": \"a\\nb\"}\n}\n\n{\"id\":2}\nnot json\n";
+        let messages = read_all(Framing::Ndjson, stream).await;
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&messages[0]).unwrap()["params"]["text"], "a\nb");
+        assert_eq!(messages[1], r#"{"id":2}"#);
+        assert_eq!(messages[2], "not json");
+    }
+
+    #[test]
+    fn test_ndjson_encodes_one_line() {
+        let frame = Framing::Ndjson.encode("{\n  \"text\": \"a\\nb\"\r\n}");
+        assert_eq!(frame, b"{  \"text\": \"a\\nb\"}\n");
+    }
+
+    #[tokio::test]
+    async fn test_content_length_headers() {
+        let stream = b"Content-Type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 8\r\n\r\n{\"id\":1}\r\n";
+        assert_eq!(read_all(Framing::ContentLength, stream).await, [r#"{"id":1}"#]);
+
+        let mut reader = BufReader::new(&b"Content-Type: x\r\n\r\n{}"[..]);
+        assert!(Framing::ContentLength.read_frame(&mut reader).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_framing() {
+        assert_eq!("NDJSON".parse::<Framing>().unwrap(), Framing::Ndjson);
+        assert_eq!("lsp".parse::<Framing>().unwrap(), Framing::ContentLength);
+        assert_eq!("length-prefixed".parse::<Framing>().unwrap(), Framing::LengthPrefixed);
+        assert!("xml".parse::<Framing>().is_err());
+    }
+}
@@ -1,73 +1,98 @@
 use crate::error::{ClientError, Result};
-use crate::transport::{McpClientTransport, TransportConfig};
+use crate::transport::sse::SseParser;
+use crate::transport::{
+    batch_messages, forward_inbound, is_request, is_response_to, request_id, InboundSender, McpClientTransport,
+    TransportConfig,
+};
 use async_trait::async_trait;
+use futures::StreamExt;
 use reqwest::{Client, header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE}};
+use serde_json::Value;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
+/// Upper bound on the delay between attempts to reopen the GET stream.
+const MAX_LISTEN_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The replies an SSE response stream is expected to carry.
+struct ReplySet {
+    /// Ids still waiting for a reply; `None` takes the first message as the reply
+    ids: Option<Vec<Value>>,
+    replies: Vec<String>,
+}
+
+impl ReplySet {
+    fn new(ids: Option<Vec<Value>>) -> Self {
+        Self { ids, replies: Vec::new() }
+    }
+
+    /// Keeps `message` if it answers one of the awaited requests.
+    fn accept(&mut self, message: &str) -> bool {
+        let ids = self.ids.get_or_insert_with(Vec::new);
+        if !self.replies.is_empty() || !ids.is_empty() {
+            let Some(index) = ids.iter().position(|id| is_response_to(message, id)) else {
+                return false;
+            };
+            ids.swap_remove(index);
+        }
+        self.replies.push(message.to_string());
+        true
+    }
+
+    fn is_complete(&self) -> bool {
+        self.ids.as_ref().is_some_and(Vec::is_empty) && !self.replies.is_empty()
+    }
+}
+
 pub struct HttpTransport {
     client: Client,
     config: TransportConfig,
     connected: Arc<Mutex<bool>>,
     session_id: Arc<Mutex<Option<String>>>,
+    /// Sent as `MCP-Protocol-Version` once `initialize` has settled it
+    protocol_version: Arc<Mutex<Option<String>>>,
+    inbound: Option<InboundSender>,
+    listener: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl HttpTransport {
     pub fn new(config: TransportConfig) -> Self {
-        let client = Client::builder()
-            .timeout(config.timeout)
-            .build()
-            .unwrap();
-
         Self {
-            client,
+            // Replaced in `connect`, where TLS settings can fail without panicking
+            client: Client::new(),
             config,
             connected: Arc::new(Mutex::new(false)),
             session_id: Arc::new(Mutex::new(None)),
+            protocol_version: Arc::new(Mutex::new(None)),
+            inbound: None,
+            listener: Mutex::new(None),
         }
     }
 
-    async fn send_http_request(&self, payload: &str) -> Result<String> {
-        let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(ACCEPT, HeaderValue::from_static("application/json, text/event-stream"));
-
-        // Add custom headers from config
-        for (key, value) in &self.config.headers {
-            headers.insert(
-                HeaderName::from_str(key).map_err(|e| ClientError::Protocol(format!("Invalid header name '{}': {}", key, e)))?,
-                HeaderValue::from_str(value).map_err(|e| ClientError::Protocol(format!("Invalid header value for '{}': {}", key, e)))?
-            );
-        }
-
-        // Add authentication token if present
-        if let Some(auth_token) = &self.config.auth_token {
-            headers.insert("Authorization", HeaderValue::from_str(auth_token)
-                .map_err(|e| ClientError::Protocol(format!("Invalid auth token: {}", e)))?);
-        }
+    fn build_headers(&self, accept: &'static str) -> Result<HeaderMap> {
+        build_headers(&self.config, accept)
+    }
 
-        // Add custom user agent if present
-        if let Some(user_agent) = &self.config.user_agent {
-            headers.insert("User-Agent", HeaderValue::from_str(user_agent)
-                .map_err(|e| ClientError::Protocol(format!("Invalid user agent: {}", e)))?);
-        }
+    async fn post(&self, payload: &str) -> Result<reqwest::Response> {
+        let mut headers = self.build_headers("application/json, text/event-stream")?;
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
-        // Add session ID if available
-        if let Some(session_id) = self.session_id.lock().await.as_ref() {
-            headers.insert("Mcp-Session-Id", HeaderValue::from_str(session_id)
-                .map_err(|e| ClientError::Protocol(format!("Invalid session ID: {}", e)))?);
-        }
+        add_session_headers(&mut headers, &self.session_id, &self.protocol_version).await?;
 
         debug!("Sending HTTP request to {}: {}", self.config.endpoint, payload);
 
         let response = self.client
             .post(&self.config.endpoint)
+            .timeout(self.config.timeout)
             .headers(headers)
             .body(payload.to_string())
             .send()
-            .await?;
+            .await
+            .map_err(sent_request_error)?;
 
         // Extract session ID from response headers if present
         if let Some(session_id) = response.headers().get("Mcp-Session-Id") {
@@ -77,6 +102,12 @@ impl HttpTransport {
             }
         }
 
+        Ok(response)
+    }
+
+    async fn send_http_request(&self, payload: &str) -> Result<String> {
+        let response = self.post(payload).await?;
+
         if response.status() == 202 {
             // HTTP 202 Accepted with no body - this is the expected response for MCP over HTTP
             debug!("Received HTTP 202 Accepted");
@@ -84,15 +115,22 @@ impl HttpTransport {
         }
 
         if !response.status().is_success() {
-            return Err(ClientError::Protocol(format!(
-                "HTTP error: {} {}",
-                response.status().as_u16(),
-                response.status().canonical_reason().unwrap_or("Unknown")
-            )));
+            return Err(ClientError::HttpStatus(response.status()));
+        }
+
+        let is_event_stream = response.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if is_event_stream {
+            debug!("Detected SSE response, reading events as they arrive");
+            let mut replies = self.read_sse_response(ReplySet::new(request_id(payload).map(|id| vec![id])), response).await?;
+            return Ok(replies.swap_remove(0));
         }
 
         let status = response.status();
-        let response_text = response.text().await?;
+        let response_text = response.text().await.map_err(sent_request_error)?;
         debug!("Received HTTP response (status {}): {}", status, response_text);
 
         // For MCP over HTTP, we might get various response formats
@@ -101,65 +139,152 @@ impl HttpTransport {
             return Ok("{}".to_string());
         }
 
-        // Check if this is a Server-Sent Events (SSE) response
-        if response_text.starts_with("event:") || response_text.contains("data:") {
-            debug!("Detected SSE response, parsing data field");
-            return self.parse_sse_response(&response_text);
+        Ok(response_text)
+    }
+
+    /// Reads an SSE response body, returning as soon as every awaited reply has arrived.
+    ///
+    /// Progress notifications and server requests that precede the replies are forwarded
+    /// to the inbound channel. If the stream is cut before the replies, it is resumed
+    /// from the last event id rather than re-sending the request.
+    ///
+    /// The POST and every resume attempt each get `config.timeout` to deliver the
+    /// replies, so a server that accepts a resume but never replays them cannot
+    /// hold the request open forever.
+    async fn read_sse_response(&self, mut replies: ReplySet, response: reqwest::Response) -> Result<Vec<String>> {
+        let mut parser = SseParser::new();
+
+        match self.read_until_reply(response, &mut parser, &mut replies).await {
+            Ok(()) if replies.is_complete() => return Ok(replies.replies),
+            Ok(()) => debug!("SSE stream closed before the response arrived"),
+            Err(e) => warn!("SSE stream interrupted before the response arrived: {}", e),
         }
 
-        Ok(response_text)
+        self.resume_sse_response(&mut parser, replies).await
     }
 
-    fn parse_sse_response(&self, sse_text: &str) -> Result<String> {
-        // Parse SSE format to extract JSON data
-        // Format: event: message\ndata: {...}\n\n
-        let mut json_data = String::new();
+    /// Reads events from `response` until all `replies` arrive or the stream ends.
+    async fn read_until_reply(
+        &self,
+        response: reqwest::Response,
+        parser: &mut SseParser,
+        replies: &mut ReplySet,
+    ) -> Result<()> {
+        let mut body = response.bytes_stream();
+
+        while let Some(chunk) = body.next().await {
+            for event in parser.feed(&chunk?) {
+                if event.data.is_empty() {
+                    continue;
+                }
 
-        for line in sse_text.lines() {
-            let line = line.trim();
-            if line.starts_with("data:") {
-                let data_part = &line[5..].trim(); // Remove "data:" prefix
-                json_data.push_str(data_part);
+                // Servers may reply to a batch with batches of their own
+                for message in batch_messages(&event.data) {
+                    if replies.accept(&message) {
+                        debug!("Extracted JSON from SSE: {}", message);
+                    } else {
+                        forward_inbound(self.inbound.as_ref(), &message);
+                    }
+                }
+                if replies.is_complete() {
+                    return Ok(());
+                }
             }
         }
 
-        if json_data.is_empty() {
-            debug!("No data field found in SSE response, returning empty JSON");
-            return Ok("{}".to_string());
+        Ok(())
+    }
+
+    /// Reconnects with `Last-Event-ID` until the awaited replies are replayed.
+    ///
+    /// The server has already accepted the request, so failures are reported as
+    /// [`ClientError::RequestInterrupted`] to stop callers from re-sending it.
+    async fn resume_sse_response(&self, parser: &mut SseParser, mut replies: ReplySet) -> Result<Vec<String>> {
+        for attempt in 1..=self.config.retry_attempts {
+            let Some(last_event_id) = parser.last_event_id().map(str::to_string) else {
+                return Err(ClientError::RequestInterrupted(
+                    "SSE stream closed before the response arrived and cannot be resumed".to_string(),
+                ));
+            };
+
+            tokio::time::sleep(parser.retry().unwrap_or(self.config.retry_delay)).await;
+            info!("Resuming SSE stream after event {} (attempt {})", last_event_id, attempt);
+
+            let response = match open_event_stream(
+                &self.client,
+                &self.config.endpoint,
+                self.build_headers("text/event-stream")?,
+                &self.session_id,
+                &self.protocol_version,
+                Some(&last_event_id),
+                Some(self.config.timeout),
+            ).await {
+                Ok(response) if response.status().is_success() => response,
+                Ok(response) => {
+                    warn!("Server refused to resume SSE stream: HTTP {}", response.status().as_u16());
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to resume SSE stream: {}", e);
+                    continue;
+                }
+            };
+
+            parser.reset();
+            match self.read_until_reply(response, parser, &mut replies).await {
+                Ok(()) if replies.is_complete() => return Ok(replies.replies),
+                Ok(()) => debug!("Resumed SSE stream closed before the response arrived"),
+                Err(e) => warn!("Resumed SSE stream interrupted: {}", e),
+            }
         }
 
-        debug!("Extracted JSON from SSE: {}", json_data);
-        Ok(json_data)
+        Err(ClientError::RequestInterrupted(format!(
+            "SSE stream could not be resumed after {} attempts",
+            self.config.retry_attempts
+        )))
     }
 
-    async fn test_connection(&self) -> Result<()> {
-        // For HTTP transport, we test by making a simple GET request to check if the endpoint exists
-        // Instead of sending a ping, we'll do a basic HTTP connectivity test
-        let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+    /// POSTs a whole batch and collects the replies to its requests.
+    async fn send_http_batch(&self, messages: &[String]) -> Result<Vec<String>> {
+        let ids: Vec<Value> = messages.iter()
+            .filter(|message| is_request(message))
+            .filter_map(|message| request_id(message))
+            .collect();
+        let response = self.post(&format!("[{}]", messages.join(","))).await?;
 
-        // Add custom headers from config
-        for (key, value) in &self.config.headers {
-            headers.insert(
-                HeaderName::from_str(key).map_err(|e| ClientError::Protocol(format!("Invalid header name '{}': {}", key, e)))?,
-                HeaderValue::from_str(value).map_err(|e| ClientError::Protocol(format!("Invalid header value for '{}': {}", key, e)))?
-            );
+        if !response.status().is_success() {
+            return Err(ClientError::HttpStatus(response.status()));
+        }
+        // A batch of notifications and responses is only acknowledged
+        if ids.is_empty() {
+            return Ok(Vec::new());
         }
 
-        // Add authentication token if present
-        if let Some(auth_token) = &self.config.auth_token {
-            headers.insert("Authorization", HeaderValue::from_str(auth_token)
-                .map_err(|e| ClientError::Protocol(format!("Invalid auth token: {}", e)))?);
+        let is_event_stream = response.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if is_event_stream {
+            return self.read_sse_response(ReplySet::new(Some(ids)), response).await;
         }
 
-        // Add custom user agent if present
-        if let Some(user_agent) = &self.config.user_agent {
-            headers.insert("User-Agent", HeaderValue::from_str(user_agent)
-                .map_err(|e| ClientError::Protocol(format!("Invalid user agent: {}", e)))?);
+        let response_text = response.text().await.map_err(sent_request_error)?;
+        debug!("Received HTTP batch response: {}", response_text);
+        if response_text.trim().is_empty() {
+            return Err(ClientError::Protocol("Empty response to a batch with requests".to_string()));
         }
+        Ok(batch_messages(&response_text))
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        // For HTTP transport, we test by making a simple GET request to check if the endpoint exists
+        // Instead of sending a ping, we'll do a basic HTTP connectivity test
+        let headers = self.build_headers("text/event-stream")?;
 
         let response = self.client
             .get(&self.config.endpoint)
+            .timeout(self.config.timeout)
             .headers(headers)
             .send()
             .await?;
@@ -177,12 +302,234 @@ impl HttpTransport {
             }
         }
     }
+
+    /// Opens the GET stream on which the server can send messages unprompted.
+    ///
+    /// Does nothing if the stream is already running.
+    async fn start_listening(&self) -> Result<()> {
+        let mut listener_guard = self.listener.lock().await;
+        if listener_guard.as_ref().is_some_and(|task| !task.is_finished()) {
+            return Ok(());
+        }
+
+        let listener = GetStream {
+            client: self.client.clone(),
+            endpoint: self.config.endpoint.clone(),
+            headers: self.build_headers("text/event-stream")?,
+            session_id: self.session_id.clone(),
+            protocol_version: self.protocol_version.clone(),
+            connected: self.connected.clone(),
+            inbound: self.inbound.clone(),
+            retry_delay: self.config.retry_delay,
+            parser: SseParser::new(),
+        };
+
+        info!("Opening GET stream for server-initiated messages");
+        *listener_guard = Some(tokio::spawn(listener.run()));
+        Ok(())
+    }
+}
+
+/// Builds the HTTP client for `config`, applying any custom TLS settings.
+///
+/// There is no overall request timeout: long-lived event streams stay open
+/// indefinitely, so individual requests apply `config.timeout` themselves.
+pub(crate) fn build_client(config: &TransportConfig) -> Result<Client> {
+    let mut builder = Client::builder().connect_timeout(config.timeout);
+
+    if config.tls.is_customized() {
+        builder = builder.use_preconfigured_tls(config.tls.client_config()?);
+    }
+
+    Ok(builder.proxy(config.proxy.reqwest_proxy()?).build()?)
+}
+
+/// Classifies a failure to POST a message or read the reply.
+///
+/// Only a failed connect is certain to have happened before the server saw the
+/// message; anything later is [`ClientError::RequestInterrupted`], so the message
+/// is not sent twice.
+pub(crate) fn sent_request_error(error: reqwest::Error) -> ClientError {
+    if error.is_connect() || error.is_builder() {
+        ClientError::Http(error)
+    } else {
+        ClientError::RequestInterrupted(error.to_string())
+    }
+}
+
+/// Builds the headers shared by every HTTP request: `Accept`, the configured
+/// custom headers, auth token and user agent.
+pub(crate) fn build_headers(config: &TransportConfig, accept: &'static str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, HeaderValue::from_static(accept));
+
+    // Add custom headers from config
+    for (key, value) in &config.headers {
+        headers.insert(
+            HeaderName::from_str(key).map_err(|e| ClientError::Protocol(format!("Invalid header name '{}': {}", key, e)))?,
+            HeaderValue::from_str(value).map_err(|e| ClientError::Protocol(format!("Invalid header value for '{}': {}", key, e)))?
+        );
+    }
+
+    // Add authentication token if present
+    if let Some(auth_token) = &config.auth_token {
+        headers.insert("Authorization", HeaderValue::from_str(auth_token)
+            .map_err(|e| ClientError::Protocol(format!("Invalid auth token: {}", e)))?);
+    }
+
+    // Add custom user agent if present
+    if let Some(user_agent) = &config.user_agent {
+        headers.insert("User-Agent", HeaderValue::from_str(user_agent)
+            .map_err(|e| ClientError::Protocol(format!("Invalid user agent: {}", e)))?);
+    }
+
+    Ok(headers)
+}
+
+/// Why a GET stream stopped without an error.
+enum StreamEnd {
+    /// The server closed the stream; it should be reopened.
+    Dropped,
+    /// The server does not offer a stream for this session.
+    Unsupported(&'static str),
+}
+
+/// Adds the session ID and negotiated protocol version, once known.
+async fn add_session_headers(
+    headers: &mut HeaderMap,
+    session_id: &Mutex<Option<String>>,
+    protocol_version: &Mutex<Option<String>>,
+) -> Result<()> {
+    if let Some(session_id) = session_id.lock().await.as_ref() {
+        headers.insert("Mcp-Session-Id", HeaderValue::from_str(session_id)
+            .map_err(|e| ClientError::Protocol(format!("Invalid session ID: {}", e)))?);
+    }
+
+    if let Some(protocol_version) = protocol_version.lock().await.as_ref() {
+        headers.insert("MCP-Protocol-Version", HeaderValue::from_str(protocol_version)
+            .map_err(|e| ClientError::Protocol(format!("Invalid protocol version: {}", e)))?);
+    }
+    Ok(())
+}
+
+/// Issues the GET that opens an SSE stream, resuming after `last_event_id` if given.
+///
+/// `timeout` bounds the whole stream, body included; without one it stays open
+/// until the server closes it.
+async fn open_event_stream(
+    client: &Client,
+    endpoint: &str,
+    mut headers: HeaderMap,
+    session_id: &Mutex<Option<String>>,
+    protocol_version: &Mutex<Option<String>>,
+    last_event_id: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response> {
+    add_session_headers(&mut headers, session_id, protocol_version).await?;
+
+    if let Some(last_event_id) = last_event_id {
+        headers.insert("Last-Event-ID", HeaderValue::from_str(last_event_id)
+            .map_err(|e| ClientError::Protocol(format!("Invalid event ID: {}", e)))?);
+    }
+
+    let mut request = client.get(endpoint).headers(headers);
+    if let Some(timeout) = timeout {
+        request = request.timeout(timeout);
+    }
+    Ok(request.send().await?)
+}
+
+/// Background task that keeps the Streamable HTTP GET stream open.
+struct GetStream {
+    client: Client,
+    endpoint: String,
+    headers: HeaderMap,
+    session_id: Arc<Mutex<Option<String>>>,
+    protocol_version: Arc<Mutex<Option<String>>>,
+    connected: Arc<Mutex<bool>>,
+    inbound: Option<InboundSender>,
+    retry_delay: Duration,
+    /// Shared across reconnections so each one resumes where the last left off
+    parser: SseParser,
+}
+
+impl GetStream {
+    async fn run(mut self) {
+        let mut delay = self.retry_delay;
+
+        while *self.connected.lock().await {
+            match self.listen().await {
+                Ok(StreamEnd::Dropped) => {
+                    debug!("GET stream closed by server, reopening");
+                    delay = self.parser.retry().unwrap_or(self.retry_delay);
+                }
+                Ok(StreamEnd::Unsupported(reason)) => {
+                    info!("Not listening for server messages: {}", reason);
+                    return;
+                }
+                Err(e) => warn!("GET stream failed: {}", e),
+            }
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_LISTEN_BACKOFF);
+        }
+    }
+
+    async fn listen(&mut self) -> Result<StreamEnd> {
+        let last_event_id = self.parser.last_event_id().map(str::to_string);
+        let response = open_event_stream(
+            &self.client,
+            &self.endpoint,
+            self.headers.clone(),
+            &self.session_id,
+            &self.protocol_version,
+            last_event_id.as_deref(),
+            None,
+        ).await?;
+
+        match response.status().as_u16() {
+            405 => return Ok(StreamEnd::Unsupported("server does not support GET streams")),
+            404 => return Ok(StreamEnd::Unsupported("session no longer exists")),
+            _ if !response.status().is_success() => {
+                return Err(ClientError::HttpStatus(response.status()));
+            }
+            _ => {}
+        }
+
+        match &last_event_id {
+            Some(last_event_id) => debug!("GET stream resumed after event {}", last_event_id),
+            None => debug!("GET stream open"),
+        }
+
+        self.parser.reset();
+        let mut body = response.bytes_stream();
+
+        while let Some(chunk) = body.next().await {
+            for event in self.parser.feed(&chunk?) {
+                if event.data.is_empty() {
+                    continue;
+                }
+
+                // Taken apart so each server request can be routed on its own
+                for message in batch_messages(&event.data) {
+                    forward_inbound(self.inbound.as_ref(), &message);
+                }
+            }
+        }
+
+        Ok(StreamEnd::Dropped)
+    }
 }
 
 #[async_trait]
 impl McpClientTransport for HttpTransport {
+    fn set_inbound_sender(&mut self, sender: InboundSender) {
+        self.inbound = Some(sender);
+    }
+
     async fn connect(&mut self) -> Result<()> {
         info!("Connecting to MCP server via HTTP: {}", self.config.endpoint);
+        self.client = build_client(&self.config)?;
 
         for attempt in 1..=self.config.retry_attempts {
             match self.test_connection().await {
@@ -206,7 +553,7 @@ impl McpClientTransport for HttpTransport {
         )))
     }
 
-    async fn send_request(&mut self, request: &str) -> Result<String> {
+    async fn send_request(&self, request: &str) -> Result<String> {
         if !self.is_connected().await {
             return Err(ClientError::Connection("Not connected".to_string()));
         }
@@ -216,7 +563,8 @@ impl McpClientTransport for HttpTransport {
                 Ok(response) => return Ok(response),
                 Err(e) => {
                     error!("Request attempt {} failed: {}", attempt, e);
-                    if attempt < self.config.retry_attempts {
+                    // A 4xx will not change on retry, and signals a legacy server to the client
+                    if attempt < self.config.retry_attempts && e.is_retryable() && !e.is_http_client_error() {
                         tokio::time::sleep(self.config.retry_delay).await;
                     } else {
                         return Err(e);
@@ -228,9 +576,53 @@ impl McpClientTransport for HttpTransport {
         Err(ClientError::Protocol("All retry attempts failed".to_string()))
     }
 
-    async fn disconnect(&mut self) -> Result<()> {
+    async fn send_message(&self, message: &str) -> Result<()> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        let response = self.post(message).await?;
+        let status = response.status();
+
+        // Notifications and responses are acknowledged with 202 Accepted and no body
+        if !status.is_success() {
+            return Err(ClientError::HttpStatus(status));
+        }
+
+        debug!("Message accepted with HTTP {}", status.as_u16());
+
+        // Once the lifecycle handshake is complete the server may start sending
+        // requests and notifications of its own
+        let method = serde_json::from_str::<Value>(message)
+            .ok()
+            .and_then(|v| v.get("method").and_then(|m| m.as_str()).map(str::to_string));
+        if method.as_deref() == Some("notifications/initialized") {
+            self.start_listening().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_batch(&self, messages: &[String]) -> Result<Vec<String>> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        // Not retried: the server may have run part of the batch already
+        self.send_http_batch(messages).await
+    }
+
+    async fn set_protocol_version(&self, version: &str) {
+        *self.protocol_version.lock().await = Some(version.to_string());
+    }
+
+    async fn disconnect(&self) -> Result<()> {
         *self.connected.lock().await = false;
+        if let Some(listener) = self.listener.lock().await.take() {
+            listener.abort();
+        }
         *self.session_id.lock().await = None;
+        *self.protocol_version.lock().await = None;
         info!("Disconnected from MCP server");
         Ok(())
     }
@@ -239,3 +631,244 @@ impl McpClientTransport for HttpTransport {
         *self.connected.lock().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers one HTTP request with `response`, returning the request's lowercased head and its body.
+    async fn serve_once(listener: &TcpListener, response: &str) -> (String, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+
+        let body_start = loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let length = head.lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .map_or(0, |length| length.trim().parse().unwrap());
+        while request.len() < body_start + length {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        stream.write_all(response.as_bytes()).await.unwrap();
+        (head, String::from_utf8_lossy(&request[body_start..]).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_batch_replies_over_sse() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/mcp", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            serve_once(&listener, "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await;
+            serve_once(
+                &listener,
+                concat!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n",
+                    "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+                    "data: [{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{}},{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}]\n\n",
+                ),
+            )
+            .await
+        });
+
+        let (inbound_tx, mut inbound_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut transport = HttpTransport::new(TransportConfig { endpoint, ..Default::default() });
+        transport.set_inbound_sender(inbound_tx);
+        transport.connect().await.unwrap();
+
+        let batch = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#.to_string(),
+        ];
+        let replies = transport.send_batch(&batch).await.unwrap();
+
+        assert_eq!(replies.len(), 2);
+        assert!(inbound_rx.recv().await.unwrap().contains("notifications/progress"));
+        let body: Value = serde_json::from_str(&server.await.unwrap().1).unwrap();
+        assert_eq!(body.as_array().map(Vec::len), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version_header_after_initialize() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let reply = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 36\r\nConnection: close\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}";
+
+        let server = tokio::spawn(async move {
+            serve_once(&listener, "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await;
+            let (before, _) = serve_once(&listener, reply).await;
+            let (after, _) = serve_once(&listener, reply).await;
+            (before, after)
+        });
+
+        let mut transport = HttpTransport::new(TransportConfig { endpoint, ..Default::default() });
+        transport.connect().await.unwrap();
+
+        let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        transport.send_request(ping).await.unwrap();
+        transport.set_protocol_version("2025-06-18").await;
+        transport.send_request(ping).await.unwrap();
+
+        let (before, after) = server.await.unwrap();
+        assert!(!before.contains("mcp-protocol-version"));
+        assert!(after.contains("mcp-protocol-version: 2025-06-18"));
+    }
+
+    #[tokio::test]
+    async fn test_resume_gives_up_when_reply_is_never_replayed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/mcp", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            serve_once(&listener, NOT_ALLOWED).await;
+            serve_once(
+                &listener,
+                concat!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n",
+                    "id: 1\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n",
+                ),
+            )
+            .await;
+
+            // Accepts the resume but never sends anything on it
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                let mut byte = [0u8; 1];
+                stream.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n").await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+
+        let mut transport = HttpTransport::new(TransportConfig {
+            endpoint,
+            timeout: Duration::from_millis(300),
+            retry_attempts: 1,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+        transport.connect().await.unwrap();
+
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"build"}}"#;
+        let result = tokio::time::timeout(Duration::from_secs(5), transport.send_request(call)).await
+            .expect("resume attempt had no deadline");
+        assert!(matches!(result, Err(ClientError::RequestInterrupted(_))));
+        server.abort();
+    }
+
+    const NOT_ALLOWED: &str = "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const ACCEPTED: &str = "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const INITIALIZED: &str = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+
+    /// A transport to `listener` that delivers server messages to the returned channel.
+    fn listening_transport(listener: &TcpListener) -> (HttpTransport, crate::transport::InboundReceiver) {
+        let endpoint = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let (inbound_tx, inbound_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut transport = HttpTransport::new(TransportConfig {
+            endpoint,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+        transport.set_inbound_sender(inbound_tx);
+        (transport, inbound_rx)
+    }
+
+    #[tokio::test]
+    async fn test_get_stream_forwards_messages_and_resumes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (mut transport, mut inbound) = listening_transport(&listener);
+
+        let server = tokio::spawn(async move {
+            serve_once(&listener, NOT_ALLOWED).await;
+            serve_once(&listener, ACCEPTED).await;
+            let (opened, _) = serve_once(
+                &listener,
+                concat!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n",
+                    "id: 7\ndata: {\"jsonrpc\":\"2.0\",\"id\":\"s1\",\"method\":\"roots/list\"}\n\n",
+                ),
+            )
+            .await;
+            let (resumed, _) = serve_once(&listener, NOT_ALLOWED).await;
+            (opened, resumed)
+        });
+
+        transport.connect().await.unwrap();
+        transport.send_message(INITIALIZED).await.unwrap();
+
+        let request = tokio::time::timeout(Duration::from_secs(5), inbound.recv()).await.unwrap().unwrap();
+        assert_eq!(request, r#"{"jsonrpc":"2.0","id":"s1","method":"roots/list"}"#);
+
+        let (opened, resumed) = server.await.unwrap();
+        assert!(opened.starts_with("get /mcp "));
+        assert!(!opened.contains("last-event-id"));
+        assert!(resumed.contains("last-event-id: 7"));
+        transport.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_stream_splits_batches() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (mut transport, mut inbound) = listening_transport(&listener);
+
+        tokio::spawn(async move {
+            serve_once(&listener, NOT_ALLOWED).await;
+            serve_once(&listener, ACCEPTED).await;
+            serve_once(
+                &listener,
+                concat!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n",
+                    "data: [{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"roots/list\"},",
+                    "{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"sampling/createMessage\"}]\n\n",
+                ),
+            )
+            .await;
+            serve_once(&listener, NOT_ALLOWED).await;
+        });
+
+        transport.connect().await.unwrap();
+        transport.send_message(INITIALIZED).await.unwrap();
+
+        for expected in ["roots/list", "sampling/createMessage"] {
+            let message = tokio::time::timeout(Duration::from_secs(5), inbound.recv()).await.unwrap().unwrap();
+            let message: Value = serde_json::from_str(&message).unwrap();
+            assert_eq!(message["method"], expected);
+        }
+        transport.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_stream_stops_when_not_allowed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (mut transport, _inbound) = listening_transport(&listener);
+
+        let server = tokio::spawn(async move {
+            serve_once(&listener, NOT_ALLOWED).await;
+            serve_once(&listener, ACCEPTED).await;
+            serve_once(&listener, NOT_ALLOWED).await;
+            // Well past the retry delay, nothing tries again
+            tokio::time::timeout(Duration::from_millis(300), listener.accept()).await.is_err()
+        });
+
+        transport.connect().await.unwrap();
+        transport.send_message(INITIALIZED).await.unwrap();
+
+        assert!(server.await.unwrap(), "GET stream was reopened after 405");
+        assert!(transport.listener.lock().await.as_ref().unwrap().is_finished());
+        transport.disconnect().await.unwrap();
+    }
+}
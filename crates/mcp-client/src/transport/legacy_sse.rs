@@ -0,0 +1,425 @@
+//! Legacy HTTP+SSE transport from the 2024-11-05 protocol revision.
+//!
+//! The client opens a long-lived `GET` event stream. The server's first event,
+//! `endpoint`, names the URL that messages are POSTed to; every reply and
+//! server-initiated message then arrives as a `message` event on the stream.
+
+use crate::error::{ClientError, Result};
+use crate::transport::http::{build_client, build_headers, sent_request_error};
+use crate::transport::pending::PendingRequests;
+use crate::transport::sse::SseParser;
+use crate::transport::{request_id, InboundSender, McpClientTransport, TransportConfig};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, Url};
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+pub struct SseTransport {
+    client: Client,
+    config: TransportConfig,
+    /// URL announced by the server's `endpoint` event
+    message_endpoint: Mutex<Option<Url>>,
+    reader_task: Mutex<Option<JoinHandle<()>>>,
+    pending: Arc<PendingRequests>,
+    connected: Arc<Mutex<bool>>,
+    inbound: Option<InboundSender>,
+}
+
+impl SseTransport {
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            // Replaced in `connect`, where TLS settings can fail without panicking
+            client: Client::new(),
+            config,
+            message_endpoint: Mutex::new(None),
+            reader_task: Mutex::new(None),
+            pending: Arc::new(PendingRequests::new()),
+            connected: Arc::new(Mutex::new(false)),
+            inbound: None,
+        }
+    }
+
+    async fn open_stream(&self) -> Result<Url> {
+        let base = Url::parse(&self.config.endpoint)
+            .map_err(|e| ClientError::Connection(format!("Invalid SSE endpoint '{}': {}", self.config.endpoint, e)))?;
+
+        let response = self.client
+            .get(base.clone())
+            .headers(build_headers(&self.config, "text/event-stream")?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(ClientError::HttpStatus(response.status()));
+        }
+
+        let (endpoint_tx, endpoint_rx) = oneshot::channel();
+        let reader = tokio::spawn(read_stream(
+            response,
+            endpoint_tx,
+            self.pending.clone(),
+            self.inbound.clone(),
+            self.connected.clone(),
+        ));
+        *self.reader_task.lock().await = Some(reader);
+
+        let endpoint = match tokio::time::timeout(self.config.timeout, endpoint_rx).await {
+            Ok(Ok(endpoint)) => endpoint,
+            Ok(Err(_)) => {
+                return Err(ClientError::Connection("SSE stream closed before the endpoint event".to_string()));
+            }
+            Err(_) => return Err(ClientError::Timeout),
+        };
+
+        message_endpoint(&base, &endpoint)
+    }
+
+    async fn post(&self, message: &str) -> Result<()> {
+        let endpoint = self.message_endpoint.lock().await.clone()
+            .ok_or_else(|| ClientError::Connection("Not connected".to_string()))?;
+
+        let mut headers = build_headers(&self.config, "application/json, text/event-stream")?;
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        debug!("Posting message to {}: {}", endpoint, message);
+        let response = self.client
+            .post(endpoint)
+            .timeout(self.config.timeout)
+            .headers(headers)
+            .body(message.to_string())
+            .send()
+            .await
+            .map_err(sent_request_error)?;
+
+        // The reply arrives on the event stream; the POST is only acknowledged
+        if !response.status().is_success() {
+            return Err(ClientError::HttpStatus(response.status()));
+        }
+        Ok(())
+    }
+
+    async fn stop_reader(&self) {
+        if let Some(reader) = self.reader_task.lock().await.take() {
+            reader.abort();
+        }
+        self.pending.fail_all();
+    }
+}
+
+/// Resolves the URL announced by the `endpoint` event against the stream URL.
+///
+/// Messages carry the auth token, so an endpoint on another origin is refused
+/// rather than handing the token to a host the user never configured.
+fn message_endpoint(base: &Url, endpoint: &str) -> Result<Url> {
+    // The endpoint is usually relative to the stream URL
+    let url = base.join(endpoint)
+        .map_err(|e| ClientError::Protocol(format!("Invalid message endpoint '{}': {}", endpoint, e)))?;
+
+    if url.origin() != base.origin() {
+        return Err(ClientError::Protocol(format!(
+            "Message endpoint '{}' is not on the same origin as {}",
+            endpoint, base
+        )));
+    }
+    Ok(url)
+}
+
+/// Owns the event stream: hands over the `endpoint` event, then routes messages.
+async fn read_stream(
+    response: reqwest::Response,
+    endpoint_tx: oneshot::Sender<String>,
+    pending: Arc<PendingRequests>,
+    inbound: Option<InboundSender>,
+    connected: Arc<Mutex<bool>>,
+) {
+    let mut endpoint_tx = Some(endpoint_tx);
+    let mut parser = SseParser::new();
+    let mut body = response.bytes_stream();
+
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                error!("SSE stream error: {}", e);
+                break;
+            }
+        };
+
+        for event in parser.feed(&chunk) {
+            match event.event.as_deref() {
+                Some("endpoint") => match endpoint_tx.take() {
+                    Some(tx) => {
+                        let _ = tx.send(event.data.trim().to_string());
+                    }
+                    None => warn!("Ignoring repeated endpoint event: {}", event.data),
+                },
+                None | Some("message") => {
+                    if event.data.is_empty() {
+                        continue;
+                    }
+                    debug!("Received message: {}", event.data);
+                    pending.route(&event.data, inbound.as_ref());
+                }
+                Some(other) => debug!("Ignoring SSE event of type {}", other),
+            }
+        }
+    }
+
+    debug!("SSE stream closed");
+    *connected.lock().await = false;
+    pending.fail_all();
+}
+
+#[async_trait]
+impl McpClientTransport for SseTransport {
+    fn set_inbound_sender(&mut self, sender: InboundSender) {
+        self.inbound = Some(sender);
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        info!("Connecting to MCP server via legacy HTTP+SSE: {}", self.config.endpoint);
+        self.client = build_client(&self.config)?;
+
+        for attempt in 1..=self.config.retry_attempts {
+            // Marked up front so a stream that closes immediately is not reported as live
+            *self.connected.lock().await = true;
+
+            match self.open_stream().await {
+                Ok(endpoint) => {
+                    info!("Server message endpoint: {}", endpoint);
+                    *self.message_endpoint.lock().await = Some(endpoint);
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Connection attempt {} failed: {}", attempt, e);
+                    *self.connected.lock().await = false;
+                    self.stop_reader().await;
+                    if attempt < self.config.retry_attempts {
+                        tokio::time::sleep(self.config.retry_delay).await;
+                    }
+                }
+            }
+        }
+
+        Err(ClientError::Connection(format!(
+            "Failed to connect after {} attempts",
+            self.config.retry_attempts
+        )))
+    }
+
+    async fn send_request(&self, request: &str) -> Result<String> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        let id = request_id(request)
+            .ok_or_else(|| ClientError::Protocol("Request has no id".to_string()))?;
+
+        let slot = self.pending.register(&id);
+        if let Err(e) = self.post(request).await {
+            self.pending.remove(&id);
+            return Err(e);
+        }
+
+        self.pending.wait(&id, slot, self.config.timeout).await
+    }
+
+    async fn send_message(&self, message: &str) -> Result<()> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        self.post(message).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        *self.connected.lock().await = false;
+        *self.message_endpoint.lock().await = None;
+        self.stop_reader().await;
+        info!("Disconnected from MCP server");
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> bool {
+        *self.connected.lock().await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::transport::InboundReceiver;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::tcp::OwnedWriteHalf;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// A legacy HTTP+SSE server on `/mcp` that announces `/messages?session=1`
+    /// and answers each POSTed request on the event stream, preceded by a
+    /// progress notification for `ping`.
+    ///
+    /// A POST to `/mcp` itself, a Streamable HTTP client's first attempt, gets
+    /// the `rejection` status line.
+    pub(crate) struct LegacyServer {
+        pub(crate) endpoint: String,
+        /// The request line of every request received
+        pub(crate) requests: mpsc::UnboundedReceiver<String>,
+    }
+
+    impl LegacyServer {
+        pub(crate) async fn start(rejection: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = format!("http://{}/mcp", listener.local_addr().unwrap());
+            let (requests_tx, requests) = mpsc::unbounded_channel();
+            let events: Arc<Mutex<Option<OwnedWriteHalf>>> = Arc::new(Mutex::new(None));
+
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let requests_tx = requests_tx.clone();
+                    let events = events.clone();
+                    tokio::spawn(async move {
+                        let (mut reader, mut writer) = stream.into_split();
+                        let (head, body) = read_request(&mut reader).await;
+                        let request_line = head.lines().next().unwrap_or_default().to_string();
+                        let _ = requests_tx.send(request_line.clone());
+
+                        if request_line.starts_with("GET /mcp ") {
+                            writer.write_all(concat!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n",
+                                "event: endpoint\ndata: /messages?session=1\n\n",
+                            ).as_bytes()).await.unwrap();
+                            *events.lock().await = Some(writer);
+                            return;
+                        }
+
+                        let status = if request_line.starts_with("POST /mcp ") { rejection } else { "202 Accepted" };
+                        let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                        writer.write_all(response.as_bytes()).await.unwrap();
+                        if status != "202 Accepted" {
+                            return;
+                        }
+
+                        let message: Value = serde_json::from_str(&body).unwrap();
+                        let (Some(id), Some(method)) = (message.get("id"), message["method"].as_str()) else {
+                            return;
+                        };
+                        let result = match method {
+                            "initialize" => json!({
+                                "protocolVersion": "2024-11-05",
+                                "capabilities": {},
+                                "serverInfo": {"name": "legacy", "version": "1.0"}
+                            }),
+                            _ => json!({"echo": method}),
+                        };
+
+                        let mut stream = String::new();
+                        if method == "ping" {
+                            let progress = json!({"jsonrpc": "2.0", "method": "notifications/progress", "params": {"progressToken": id}});
+                            stream.push_str(&format!("event: message\ndata: {}\n\n", progress));
+                        }
+                        let reply = json!({"jsonrpc": "2.0", "id": id, "result": result});
+                        stream.push_str(&format!("event: message\ndata: {}\n\n", reply));
+                        if let Some(events) = events.lock().await.as_mut() {
+                            events.write_all(stream.as_bytes()).await.unwrap();
+                        }
+                    });
+                }
+            });
+
+            Self { endpoint, requests }
+        }
+    }
+
+    /// Reads one HTTP request, returning its lowercased head and its body.
+    async fn read_request(reader: &mut (impl AsyncReadExt + Unpin)) -> (String, String) {
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        let body_start = loop {
+            let read = reader.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..body_start]).to_string();
+        let length = head.to_lowercase().lines()
+            .find_map(|line| line.strip_prefix("content-length:").map(|length| length.trim().parse().unwrap()))
+            .unwrap_or(0);
+        while request.len() < body_start + length {
+            let read = reader.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+        (head, String::from_utf8_lossy(&request[body_start..]).to_string())
+    }
+
+    async fn connect(server: &LegacyServer) -> (SseTransport, InboundReceiver) {
+        let (inbound_tx, inbound_rx) = mpsc::unbounded_channel();
+        let mut transport = SseTransport::new(TransportConfig {
+            endpoint: server.endpoint.clone(),
+            timeout: Duration::from_secs(5),
+            retry_attempts: 1,
+            ..Default::default()
+        });
+        transport.set_inbound_sender(inbound_tx);
+        transport.connect().await.unwrap();
+        (transport, inbound_rx)
+    }
+
+    #[test]
+    fn test_message_endpoint_must_share_origin() {
+        let base = Url::parse("http://mcp.example.com:8080/sse").unwrap();
+
+        let relative = message_endpoint(&base, "/messages?session=1").unwrap();
+        assert_eq!(relative.as_str(), "http://mcp.example.com:8080/messages?session=1");
+        assert!(message_endpoint(&base, "http://mcp.example.com:8080/messages").is_ok());
+
+        for elsewhere in [
+            "http://attacker.example:8080/messages",
+            "http://mcp.example.com:9090/messages",
+            "https://mcp.example.com:8080/messages",
+            "//attacker.example/messages",
+        ] {
+            assert!(matches!(message_endpoint(&base, elsewhere), Err(ClientError::Protocol(_))), "{}", elsewhere);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_posts_to_announced_endpoint() {
+        let mut server = LegacyServer::start("405 Method Not Allowed").await;
+        let (transport, _inbound) = connect(&server).await;
+
+        let reply = transport.send_request(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#).await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&reply).unwrap()["result"]["echo"], "tools/list");
+
+        assert!(server.requests.recv().await.unwrap().starts_with("GET /mcp "));
+        assert!(server.requests.recv().await.unwrap().starts_with("POST /messages?session=1 "));
+        transport.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_correlates_replies_and_forwards_server_messages() {
+        let server = LegacyServer::start("405 Method Not Allowed").await;
+        let (transport, mut inbound) = connect(&server).await;
+
+        let (first, second) = tokio::join!(
+            transport.send_request(r#"{"jsonrpc":"2.0","id":"a","method":"ping"}"#),
+            transport.send_request(r#"{"jsonrpc":"2.0","id":"b","method":"resources/list"}"#),
+        );
+        let first: Value = serde_json::from_str(&first.unwrap()).unwrap();
+        let second: Value = serde_json::from_str(&second.unwrap()).unwrap();
+        assert_eq!((first["id"].as_str(), first["result"]["echo"].as_str()), (Some("a"), Some("ping")));
+        assert_eq!((second["id"].as_str(), second["result"]["echo"].as_str()), (Some("b"), Some("resources/list")));
+
+        // Messages that answer no request go to the inbound channel
+        let progress: Value = serde_json::from_str(&inbound.recv().await.unwrap()).unwrap();
+        assert_eq!(progress["method"], "notifications/progress");
+        assert_eq!(progress["params"]["progressToken"], "a");
+        transport.disconnect().await.unwrap();
+    }
+}
@@ -1,22 +1,76 @@
 use crate::error::Result;
 use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose};
+use rmcp::model::LoggingLevel;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
 
+pub mod framing;
 pub mod http;
+pub mod legacy_sse;
+mod pending;
+pub mod proxy;
+pub mod sse;
+mod stderr;
 pub mod stdio;
 pub mod tcp;
+pub mod tls;
+#[cfg(unix)]
+pub mod unix;
+pub mod websocket;
 
+pub use framing::Framing;
 pub use http::HttpTransport;
-pub use stdio::StdioTransport;
+pub use legacy_sse::SseTransport;
+pub use proxy::ProxyConfig;
+pub use stderr::StderrHandling;
+pub use stdio::{RestartPolicy, StdioConfig, StdioTransport};
 pub use tcp::TcpTransport;
+pub use tls::{TlsConfig, TlsVersion};
+#[cfg(unix)]
+pub use unix::UnixTransport;
+pub use websocket::WebSocketTransport;
+
+/// Channel on which transports deliver server-initiated requests and notifications.
+pub type InboundSender = mpsc::UnboundedSender<String>;
+pub type InboundReceiver = mpsc::UnboundedReceiver<String>;
 
 #[async_trait]
 pub trait McpClientTransport: Send + Sync {
+    /// Registers the channel that receives messages not answering one of our requests.
+    fn set_inbound_sender(&mut self, sender: InboundSender);
     async fn connect(&mut self) -> Result<()>;
-    async fn send_request(&mut self, request: &str) -> Result<String>;
-    async fn disconnect(&mut self) -> Result<()>;
+    async fn send_request(&self, request: &str) -> Result<String>;
+    /// Sends a message that expects no reply, such as a notification.
+    async fn send_message(&self, message: &str) -> Result<()>;
+    /// Sends a JSON-RPC batch and returns the replies to its requests, in no particular order.
+    ///
+    /// By default the messages go out one by one in batch order, with all the
+    /// requests in flight at once. A request that fails is answered with an error
+    /// reply of its own, so the replies to the others are kept.
+    async fn send_batch(&self, messages: &[String]) -> Result<Vec<String>> {
+        // join_all first polls the sends in order, so a `notifications/cancelled`
+        // goes out after the request it cancels
+        let sends = messages.iter().map(|message| async move {
+            if !is_request(message) {
+                if let Err(e) = self.send_message(message).await {
+                    warn!("Failed to send batched message: {}", e);
+                }
+                return None;
+            }
+            Some(match self.send_request(message).await {
+                Ok(reply) => reply,
+                Err(e) => failed_request_reply(message, &e),
+            })
+        });
+        Ok(futures::future::join_all(sends).await.into_iter().flatten().collect())
+    }
+    /// Records the protocol version negotiated in `initialize`, for transports that announce it.
+    async fn set_protocol_version(&self, _version: &str) {}
+    async fn disconnect(&self) -> Result<()>;
     async fn is_connected(&self) -> bool;
 }
 
@@ -29,6 +83,11 @@ pub struct TransportConfig {
     pub headers: HashMap<String, String>,
     pub auth_token: Option<String>,
     pub user_agent: Option<String>,
+    pub tls: TlsConfig,
+    pub proxy: ProxyConfig,
+    pub stdio: StdioConfig,
+    /// Message framing for stream transports (STDIO, TCP and Unix sockets)
+    pub framing: Framing,
 }
 
 impl Default for TransportConfig {
@@ -41,6 +100,10 @@ impl Default for TransportConfig {
             headers: HashMap::new(),
             auth_token: None,
             user_agent: Some("mcp-connect-client/0.1.0".to_string()),
+            tls: TlsConfig::default(),
+            proxy: ProxyConfig::default(),
+            stdio: StdioConfig::default(),
+            framing: Framing::default(),
         }
     }
 }
@@ -81,12 +144,38 @@ impl TransportConfig {
         self.user_agent = Some(user_agent);
         self
     }
+
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn with_stdio(mut self, stdio: StdioConfig) -> Self {
+        self.stdio = stdio;
+        self
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
 }
 
 pub async fn create_transport(
     transport_type: mcp_types::TransportType,
     config: TransportConfig,
 ) -> Result<Box<dyn McpClientTransport>> {
+    // A `unix:` endpoint selects a Unix socket even when TCP was requested
+    let transport_type = match transport_type {
+        mcp_types::TransportType::Tcp if config.endpoint.starts_with("unix:") => mcp_types::TransportType::Unix,
+        other => other,
+    };
+
     match transport_type {
         mcp_types::TransportType::Http => {
             Ok(Box::new(HttpTransport::new(config)))
@@ -97,5 +186,175 @@ pub async fn create_transport(
         mcp_types::TransportType::Tcp => {
             Ok(Box::new(TcpTransport::new(config)))
         }
+        mcp_types::TransportType::Sse => {
+            Ok(Box::new(SseTransport::new(config)))
+        }
+        mcp_types::TransportType::WebSocket => {
+            Ok(Box::new(WebSocketTransport::new(config)))
+        }
+        #[cfg(unix)]
+        mcp_types::TransportType::Unix => {
+            Ok(Box::new(UnixTransport::new(config)))
+        }
+        #[cfg(not(unix))]
+        mcp_types::TransportType::Unix => {
+            Err(crate::error::ClientError::Connection("Unix sockets are not supported on this platform".to_string()))
+        }
+    }
+}
+
+/// Extracts the JSON-RPC id of an outgoing request, if it has one.
+pub(crate) fn request_id(request: &str) -> Option<Value> {
+    serde_json::from_str::<Value>(request)
+        .ok()
+        .and_then(|v| v.get("id").cloned())
+}
+
+/// Returns true if `message` is a request, i.e. it has both a method and an id.
+pub(crate) fn is_request(message: &str) -> bool {
+    serde_json::from_str::<Value>(message)
+        .is_ok_and(|v| v.get("method").is_some() && v.get("id").is_some())
+}
+
+/// The error reply standing in for a request that could not be completed.
+fn failed_request_reply(request: &str, error: &crate::error::ClientError) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": request_id(request).unwrap_or(Value::Null),
+        "error": {"code": -32603, "message": error.to_string()}
+    })
+    .to_string()
+}
+
+/// Splits a JSON-RPC batch into its messages; anything else comes back unchanged.
+pub(crate) fn batch_messages(message: &str) -> Vec<String> {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Array(entries)) => entries.iter().map(Value::to_string).collect(),
+        _ => vec![message.to_string()],
+    }
+}
+
+/// Returns true if `message` is the response (rather than a request) carrying `id`.
+pub(crate) fn is_response_to(message: &str, id: &Value) -> bool {
+    serde_json::from_str::<Value>(message)
+        .map(|v| v.get("method").is_none() && v.get("id") == Some(id))
+        .unwrap_or(false)
+}
+
+/// Builds an MCP `notifications/message` log notification.
+pub(crate) fn log_notification(level: mcp_types::LogLevel, logger: &str, data: &str) -> String {
+    // The spec's level names, which differ from ours ("warning", not "warn")
+    let level = match level {
+        mcp_types::LogLevel::Debug => LoggingLevel::Debug,
+        mcp_types::LogLevel::Info => LoggingLevel::Info,
+        mcp_types::LogLevel::Warn => LoggingLevel::Warning,
+        mcp_types::LogLevel::Error => LoggingLevel::Error,
+    };
+
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/message",
+        "params": {
+            "level": level,
+            "logger": logger,
+            "data": data
+        }
+    })
+    .to_string()
+}
+
+/// The client's opening handshake, repeated when a transport has to start a new
+/// server session: a restarted process or a reopened socket.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Handshake {
+    pub(crate) initialize: Option<Value>,
+    pub(crate) initialized: Option<String>,
+}
+
+impl Handshake {
+    /// Remembers `message` if it is the client's `initialize` or `initialized`.
+    pub(crate) fn record(&mut self, message: &str) {
+        let Ok(parsed) = serde_json::from_str::<Value>(message) else {
+            return;
+        };
+        match parsed.get("method").and_then(Value::as_str) {
+            Some("initialize") => *self = Handshake { initialize: Some(parsed), initialized: None },
+            Some("notifications/initialized") => self.initialized = Some(message.to_string()),
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn forward_inbound(inbound: Option<&InboundSender>, message: &str) {
+    match inbound {
+        Some(sender) => {
+            debug!("Forwarding server message: {}", message);
+            let _ = sender.send(message.to_string());
+        }
+        None => debug!("No inbound channel, dropping server message: {}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ClientError;
+    use serde_json::json;
+    use tokio::sync::Mutex;
+
+    /// Records messages as they are written; request 1 is slow and request 2 fails.
+    #[derive(Default)]
+    struct Recorder {
+        written: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl McpClientTransport for Recorder {
+        fn set_inbound_sender(&mut self, _sender: InboundSender) {}
+
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_request(&self, request: &str) -> Result<String> {
+            self.written.lock().await.push(request.to_string());
+            let id = request_id(request).unwrap();
+            if id == json!(2) {
+                return Err(ClientError::Connection("Not connected".to_string()));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(json!({"jsonrpc": "2.0", "id": id, "result": {}}).to_string())
+        }
+
+        async fn send_message(&self, message: &str) -> Result<()> {
+            self.written.lock().await.push(message.to_string());
+            Ok(())
+        }
+
+        async fn disconnect(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn is_connected(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_batch_keeps_order_and_every_reply() {
+        let transport = Recorder::default();
+        let batch = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":1}}"#.to_string(),
+            r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#.to_string(),
+        ];
+
+        let replies = transport.send_batch(&batch).await.unwrap();
+
+        assert_eq!(*transport.written.lock().await, batch);
+        let replies: Vec<Value> = replies.iter().map(|reply| serde_json::from_str(reply).unwrap()).collect();
+        assert_eq!(replies[0], json!({"jsonrpc": "2.0", "id": 1, "result": {}}));
+        assert_eq!(replies[1]["id"], 2);
+        assert_eq!(replies[1]["error"]["code"], -32603);
     }
 }
@@ -0,0 +1,286 @@
+//! Request/response correlation for transports that share one long-lived stream.
+//!
+//! A single reader task owns the read side of the connection. Responses are
+//! matched to waiting requests by JSON-RPC id; everything else the server sends
+//! (notifications and server-initiated requests) goes to the inbound channel.
+
+use crate::error::{ClientError, Result};
+use crate::transport::{batch_messages, forward_inbound, Framing, InboundSender};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncBufRead;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// How many abandoned request ids are remembered to swallow their late responses.
+const MAX_ABANDONED: usize = 256;
+
+#[derive(Default)]
+struct Slots {
+    waiting: HashMap<String, oneshot::Sender<String>>,
+    /// Requests whose caller gave up, oldest first
+    abandoned: VecDeque<String>,
+}
+
+/// Slots for requests that are waiting on a response, keyed by JSON-RPC id.
+///
+/// The lock is never held across an await, so a plain mutex lets [`Slot`]
+/// clean up when its caller is cancelled.
+#[derive(Default)]
+pub(crate) struct PendingRequests {
+    slots: std::sync::Mutex<Slots>,
+}
+
+/// A registered request's claim on its response.
+///
+/// Dropping it before the response arrives, because the caller was cancelled or
+/// timed out, abandons the request so that a late response is discarded instead
+/// of being mistaken for a server-initiated message.
+pub(crate) struct Slot<'a> {
+    pending: &'a PendingRequests,
+    id: String,
+    receiver: oneshot::Receiver<String>,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.pending.abandon(&self.id);
+    }
+}
+
+impl PendingRequests {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn slots(&self) -> std::sync::MutexGuard<'_, Slots> {
+        self.slots.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Reserves a slot for `id`; the returned slot resolves with the raw response.
+    pub(crate) fn register(&self, id: &Value) -> Slot<'_> {
+        let (tx, receiver) = oneshot::channel();
+        if self.slots().waiting.insert(id.to_string(), tx).is_some() {
+            warn!("Request id {} was already pending, replacing it", id);
+        }
+        Slot { pending: self, id: id.to_string(), receiver }
+    }
+
+    /// Drops the slot for `id`, e.g. because the request could not be sent.
+    pub(crate) fn remove(&self, id: &Value) {
+        self.slots().waiting.remove(&id.to_string());
+    }
+
+    /// Stops waiting for `id` and remembers it, so its response is discarded if it still comes.
+    fn abandon(&self, id: &str) {
+        let mut slots = self.slots();
+        if slots.waiting.remove(id).is_none() {
+            return; // Answered, or removed deliberately
+        }
+
+        debug!("Abandoned request {}", id);
+        if slots.abandoned.len() == MAX_ABANDONED {
+            slots.abandoned.pop_front();
+        }
+        slots.abandoned.push_back(id.to_string());
+    }
+
+    /// Waits for the response registered under `id`, giving up after `timeout`.
+    ///
+    /// The request has already been written, so failures are reported as
+    /// [`ClientError::RequestInterrupted`] to stop callers from re-sending it.
+    pub(crate) async fn wait(&self, id: &Value, mut slot: Slot<'_>, timeout: Duration) -> Result<String> {
+        match tokio::time::timeout(timeout, &mut slot.receiver).await {
+            Ok(Ok(response)) => {
+                debug!("Received response: {}", response);
+                Ok(response)
+            }
+            Ok(Err(_)) => {
+                error!("Connection closed before response to {} arrived", id);
+                Err(ClientError::RequestInterrupted("Connection closed before the response arrived".to_string()))
+            }
+            Err(_) => {
+                error!("Timeout waiting for response to {}", id);
+                Err(ClientError::RequestInterrupted(format!("No response within {:?}", timeout)))
+            }
+        }
+    }
+
+    /// Hands `message` to the request waiting for it.
+    ///
+    /// Returns false if the message is not a response to a pending request.
+    pub(crate) fn complete(&self, message: &str) -> bool {
+        let Ok(parsed) = serde_json::from_str::<Value>(message) else {
+            return false;
+        };
+        if parsed.get("method").is_some() {
+            return false;
+        }
+        let Some(id) = parsed.get("id") else {
+            return false;
+        };
+
+        let id = id.to_string();
+        let mut slots = self.slots();
+        if let Some(slot) = slots.waiting.remove(&id) {
+            let _ = slot.send(message.to_string());
+            return true;
+        }
+
+        match slots.abandoned.iter().position(|abandoned| *abandoned == id) {
+            Some(index) => {
+                slots.abandoned.remove(index);
+                debug!("Discarding late response to abandoned request {}", id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Completes the requests `message` answers and forwards the rest to `inbound`.
+    ///
+    /// A batch is taken apart, since its replies may belong to different callers.
+    pub(crate) fn route(&self, message: &str, inbound: Option<&InboundSender>) {
+        for message in batch_messages(message) {
+            if !self.complete(&message) {
+                forward_inbound(inbound, &message);
+            }
+        }
+    }
+
+    /// Wakes every waiting request with a closed-channel error.
+    pub(crate) fn fail_all(&self) {
+        let mut slots = self.slots();
+        slots.waiting.clear();
+        slots.abandoned.clear();
+    }
+}
+
+/// Spawns the task that owns the read side of a stream of `framing` delimited messages.
+///
+/// When the stream ends, `connected` is cleared and all pending requests fail.
+pub(crate) fn spawn_reader<R>(
+    mut reader: R,
+    framing: Framing,
+    pending: Arc<PendingRequests>,
+    inbound: Option<InboundSender>,
+    connected: Arc<Mutex<bool>>,
+) -> JoinHandle<()>
+where
+    R: AsyncBufRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            match framing.read_frame(&mut reader).await {
+                Ok(None) => {
+                    debug!("Server closed the stream");
+                    break;
+                }
+                Ok(Some(message)) => {
+                    let message = message.trim();
+                    if message.is_empty() {
+                        continue;
+                    }
+
+                    debug!("Received message: {}", message);
+                    pending.route(message, inbound.as_ref());
+                }
+                Err(e) => {
+                    error!("IO error reading from server: {}", e);
+                    break;
+                }
+            }
+        }
+
+        *connected.lock().await = false;
+        pending.fail_all();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_reader_routes_responses_and_notifications() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let pending = Arc::new(PendingRequests::new());
+        let connected = Arc::new(Mutex::new(true));
+        let (inbound_tx, mut inbound_rx) = mpsc::unbounded_channel();
+
+        let slot = pending.register(&json!(7));
+        let reader = spawn_reader(
+            tokio::io::BufReader::new(client),
+            Framing::Ndjson,
+            pending.clone(),
+            Some(inbound_tx),
+            connected.clone(),
+        );
+
+        // Notification first, then the response, in a single write
+        server
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n{\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{}}\n")
+            .await
+            .unwrap();
+
+        let response = pending.wait(&json!(7), slot, Duration::from_secs(1)).await.unwrap();
+        assert!(response.contains("\"id\":7"));
+        assert!(inbound_rx.recv().await.unwrap().contains("notifications/progress"));
+
+        drop(server);
+        reader.await.unwrap();
+        assert!(!*connected.lock().await);
+    }
+
+    #[tokio::test]
+    async fn test_route_splits_batches() {
+        let pending = PendingRequests::new();
+        let (inbound_tx, mut inbound_rx) = mpsc::unbounded_channel();
+        let first = pending.register(&json!(1));
+        let second = pending.register(&json!(2));
+
+        pending.route(
+            r#"[{"jsonrpc":"2.0","id":2,"result":{}},{"jsonrpc":"2.0","method":"notifications/progress"},{"jsonrpc":"2.0","id":1,"result":{}}]"#,
+            Some(&inbound_tx),
+        );
+
+        let timeout = Duration::from_secs(1);
+        assert!(pending.wait(&json!(1), first, timeout).await.unwrap().contains("\"id\":1"));
+        assert!(pending.wait(&json!(2), second, timeout).await.unwrap().contains("\"id\":2"));
+        assert!(inbound_rx.recv().await.unwrap().contains("notifications/progress"));
+    }
+
+    #[tokio::test]
+    async fn test_late_response_to_cancelled_request_is_discarded() {
+        let pending = PendingRequests::new();
+        let (inbound_tx, mut inbound_rx) = mpsc::unbounded_channel();
+
+        let id = json!(3);
+        let slot = pending.register(&id);
+        let waiting = pending.wait(&id, slot, Duration::from_secs(5));
+        assert!(tokio::time::timeout(Duration::from_millis(10), waiting).await.is_err());
+
+        pending.route(r#"{"jsonrpc":"2.0","id":3,"result":{}}"#, Some(&inbound_tx));
+        pending.route(r#"{"jsonrpc":"2.0","id":3,"result":{}}"#, Some(&inbound_tx));
+
+        // The first late response is swallowed, a repeat is not
+        assert!(inbound_rx.recv().await.unwrap().contains("\"id\":3"));
+        assert!(inbound_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pending_requests_fail_on_close() {
+        let pending = PendingRequests::new();
+        let slot = pending.register(&json!("a"));
+        pending.fail_all();
+
+        let result = pending.wait(&json!("a"), slot, Duration::from_secs(1)).await;
+        assert!(matches!(result, Err(ClientError::RequestInterrupted(_))));
+    }
+}
@@ -0,0 +1,336 @@
+//! Egress through an HTTP CONNECT or SOCKS5 proxy.
+//!
+//! An explicit proxy URL wins; otherwise the usual `HTTPS_PROXY`, `HTTP_PROXY`
+//! and `ALL_PROXY` variables are consulted. Hosts matching the `no_proxy` list
+//! or `NO_PROXY` are always dialed directly.
+
+use crate::error::{ClientError, Result};
+use base64::{engine::general_purpose, Engine as _};
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use tracing::{debug, warn};
+
+/// Upper bound on the proxy's reply to `CONNECT`, to stop a misbehaving proxy early.
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct ProxyConfig {
+    /// `http://`, `socks5://` (resolve locally) or `socks5h://` (resolve at the proxy).
+    /// When unset, the proxy environment variables apply.
+    pub url: Option<String>,
+    /// Proxy username; overrides credentials embedded in the URL
+    pub username: Option<String>,
+    /// Proxy password
+    pub password: Option<String>,
+    /// Hosts reached without the proxy, in addition to `NO_PROXY`.
+    /// `example.com` also covers its subdomains and `*` disables proxying.
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    pub fn with_url(mut self, url: String) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    pub fn with_credentials(mut self, username: String, password: Option<String>) -> Self {
+        self.username = Some(username);
+        self.password = password;
+        self
+    }
+
+    pub fn with_no_proxy(mut self, host: String) -> Self {
+        self.no_proxy.push(host);
+        self
+    }
+
+    /// Checks the explicit proxy URL, so a typo or a scheme no transport can
+    /// dial fails before connecting rather than silently sending traffic direct.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if let Some(url) = &self.url {
+            parse_proxy_url(url)?;
+        }
+        Ok(())
+    }
+
+    /// The proxy to reach `target` through, with credentials attached, or `None`
+    /// to connect directly.
+    pub fn proxy_for(&self, target: &Url) -> Result<Option<Url>> {
+        let Some(host) = target.host_str() else {
+            return Ok(None);
+        };
+        if self.bypasses(host) {
+            debug!("Bypassing proxy for {}", host);
+            return Ok(None);
+        }
+
+        let configured = match &self.url {
+            Some(url) => Some(url.clone()),
+            None => env_proxy(target.scheme()),
+        };
+        let Some(configured) = configured else {
+            return Ok(None);
+        };
+
+        let mut url = parse_proxy_url(&configured)?;
+        if let Some(username) = &self.username {
+            url.set_username(username)
+                .and_then(|_| url.set_password(self.password.as_deref()))
+                .map_err(|_| ClientError::Connection(format!("Cannot attach credentials to proxy URL '{}'", configured)))?;
+        }
+        Ok(Some(url))
+    }
+
+    /// These settings as a reqwest proxy, replacing reqwest's own environment lookup.
+    pub fn reqwest_proxy(&self) -> Result<reqwest::Proxy> {
+        self.validate()?;
+        let config = self.clone();
+        Ok(reqwest::Proxy::custom(move |url| match config.proxy_for(url) {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                warn!("Ignoring proxy setting: {}", e);
+                None
+            }
+        }))
+    }
+
+    /// True if `host` matches the configured or environment `no_proxy` lists.
+    fn bypasses(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+        let from_env = env_var(&["NO_PROXY", "no_proxy"]).unwrap_or_default();
+
+        self.no_proxy.iter()
+            .map(String::as_str)
+            .chain(from_env.split(','))
+            .map(|entry| entry.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| {
+                entry == "*"
+                    || host == entry
+                    || host.strip_suffix(&entry).is_some_and(|prefix| prefix.ends_with('.'))
+            })
+    }
+
+    /// Opens a TCP connection to `host:port`, tunnelled through the proxy if one applies.
+    /// `scheme` selects which proxy environment variable is consulted.
+    pub(crate) async fn connect(&self, scheme: &str, host: &str, port: u16) -> Result<TcpStream> {
+        let target = target_url(scheme, host, port)?;
+        let Some(proxy) = self.proxy_for(&target)? else {
+            return Ok(TcpStream::connect((host, port)).await?);
+        };
+
+        let proxy_host = proxy.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+        let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+        debug!("Connecting to {}:{} via proxy {}:{}", host, port, proxy_host, proxy_port);
+
+        let stream = TcpStream::connect((proxy_host, proxy_port)).await
+            .map_err(|e| ClientError::Connection(format!("Failed to reach proxy {}:{}: {}", proxy_host, proxy_port, e)))?;
+        let credentials = credentials(&proxy);
+
+        match proxy.scheme() {
+            "http" => http_connect(stream, host, port, credentials).await,
+            "socks5" => {
+                // Plain socks5 means the proxy is handed an address, not a name
+                let address = tokio::net::lookup_host((host, port)).await?
+                    .next()
+                    .ok_or_else(|| ClientError::Connection(format!("Could not resolve {}", host)))?;
+                socks5_connect(stream, address, credentials).await
+            }
+            // parse_proxy_url admits no other scheme
+            _ => socks5_connect(stream, (host.to_string(), port), credentials).await,
+        }
+    }
+}
+
+/// Parses a proxy URL, accepting only the schemes [`ProxyConfig::connect`] can
+/// dial so every transport agrees on what is valid.
+fn parse_proxy_url(url: &str) -> Result<Url> {
+    // Environment variables commonly omit the scheme
+    let with_scheme = if url.contains("://") { url.to_string() } else { format!("http://{}", url) };
+    let parsed = Url::parse(&with_scheme)
+        .map_err(|e| ClientError::Connection(format!("Invalid proxy URL '{}': {}", url, e)))?;
+
+    match parsed.scheme() {
+        "http" | "socks5" | "socks5h" if parsed.host_str().is_some() => Ok(parsed),
+        "http" | "socks5" | "socks5h" => {
+            Err(ClientError::Connection(format!("Invalid proxy URL '{}': missing host", url)))
+        }
+        other => Err(ClientError::Connection(format!(
+            "Unsupported proxy scheme '{}': expected http, socks5 or socks5h",
+            other
+        ))),
+    }
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names.iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// Proxy from the environment for a target scheme; plain `http` only honors
+/// `HTTP_PROXY`, everything else (`https`, `wss`, raw TCP) honors `HTTPS_PROXY`.
+fn env_proxy(scheme: &str) -> Option<String> {
+    let specific = match scheme {
+        "http" | "ws" => env_var(&["HTTP_PROXY", "http_proxy"]),
+        _ => env_var(&["HTTPS_PROXY", "https_proxy"]),
+    };
+    specific.or_else(|| env_var(&["ALL_PROXY", "all_proxy"]))
+}
+
+fn target_url(scheme: &str, host: &str, port: u16) -> Result<Url> {
+    let host = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+    Url::parse(&format!("{}://{}:{}", scheme, host, port))
+        .map_err(|e| ClientError::Connection(format!("Invalid address {}:{}: {}", host, port, e)))
+}
+
+/// Percent-decoded username and password from the proxy URL.
+fn credentials(proxy: &Url) -> Option<(String, String)> {
+    if proxy.username().is_empty() {
+        return None;
+    }
+    let decode = |value: &str| urlencoding::decode(value).map(|v| v.into_owned()).unwrap_or_else(|_| value.to_string());
+    Some((decode(proxy.username()), decode(proxy.password().unwrap_or(""))))
+}
+
+/// Asks an HTTP proxy to open a tunnel with `CONNECT`.
+async fn http_connect(
+    mut stream: TcpStream,
+    host: &str,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> Result<TcpStream> {
+    let authority = if host.contains(':') { format!("[{}]:{}", host, port) } else { format!("{}:{}", host, port) };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some((username, password)) = credentials {
+        let token = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing from the tunnelled stream is consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err(ClientError::Connection("Proxy sent an oversized CONNECT response".to_string()));
+        }
+        let mut byte = [0u8; 1];
+        if stream.read(&mut byte).await? == 0 {
+            return Err(ClientError::Connection("Proxy closed the connection during CONNECT".to_string()));
+        }
+        response.push(byte[0]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    match status {
+        "200" => Ok(stream),
+        "407" => Err(ClientError::Auth(format!("Proxy authentication required: {}", status_line))),
+        _ => Err(ClientError::Connection(format!("Proxy refused CONNECT to {}: {}", authority, status_line))),
+    }
+}
+
+async fn socks5_connect<'t, T>(
+    stream: TcpStream,
+    target: T,
+    credentials: Option<(String, String)>,
+) -> Result<TcpStream>
+where
+    T: tokio_socks::IntoTargetAddr<'t>,
+{
+    let stream = match credentials {
+        Some((username, password)) => {
+            Socks5Stream::connect_with_password_and_socket(stream, target, &username, &password).await
+        }
+        None => Socks5Stream::connect_with_socket(stream, target).await,
+    };
+
+    match stream {
+        Ok(stream) => Ok(stream.into_inner()),
+        Err(tokio_socks::Error::PasswordAuthFailure(code)) => {
+            Err(ClientError::Auth(format!("SOCKS5 proxy rejected the credentials (code {})", code)))
+        }
+        Err(e) => {
+            warn!("SOCKS5 proxy connection failed: {}", e);
+            Err(ClientError::Connection(format!("SOCKS5 proxy connection failed: {}", e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_no_proxy_matching() {
+        let config = ProxyConfig::default()
+            .with_url("http://proxy.corp:3128".to_string())
+            .with_no_proxy(".internal.example".to_string())
+            .with_no_proxy("localhost".to_string());
+
+        assert!(config.bypasses("localhost"));
+        assert!(config.bypasses("api.internal.example"));
+        assert!(config.bypasses("internal.example"));
+        assert!(!config.bypasses("notinternal.example"));
+
+        let target = Url::parse("https://mcp.example.com/mcp").unwrap();
+        let proxy = config.proxy_for(&target).unwrap().unwrap();
+        assert_eq!(proxy.host_str(), Some("proxy.corp"));
+        assert_eq!(proxy.port(), Some(3128));
+    }
+
+    #[test]
+    fn test_rejects_unknown_proxy_scheme() {
+        let config = ProxyConfig::default().with_url("ftp://proxy.corp".to_string());
+        assert!(config.validate().is_err());
+        assert!(ProxyConfig::default().with_url("proxy.corp:3128".to_string()).validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_https_proxy_for_every_transport() {
+        let config = ProxyConfig::default().with_url("https://proxy.corp:3128".to_string());
+        assert!(config.validate().is_err());
+        assert!(config.reqwest_proxy().is_err());
+
+        // Rejected while reading the config, before anything is dialed
+        let err = config.connect("tcp", "mcp.example.com", 9000).await.unwrap_err();
+        assert!(err.to_string().contains("Unsupported proxy scheme 'https'"));
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_tunnel() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+
+        let proxy = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut byte = [0u8; 1];
+                stream.read_exact(&mut byte).await.unwrap();
+                request.push(byte[0]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("CONNECT mcp.example.com:9000 HTTP/1.1\r\n"));
+            // "user:p@ss" in base64
+            assert!(request.contains("Proxy-Authorization: Basic dXNlcjpwQHNz\r\n"));
+
+            stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello").await.unwrap();
+        });
+
+        let config = ProxyConfig::default()
+            .with_url(format!("http://{}", proxy_addr))
+            .with_credentials("user".to_string(), Some("p@ss".to_string()));
+        let mut stream = config.connect("tcp", "mcp.example.com", 9000).await.unwrap();
+
+        let mut greeting = [0u8; 5];
+        stream.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"hello");
+        proxy.await.unwrap();
+    }
+}
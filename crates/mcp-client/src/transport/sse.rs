@@ -0,0 +1,180 @@
+//! Incremental parser for `text/event-stream` bodies.
+//!
+//! Follows the WHATWG event-stream format: events are separated by a blank line,
+//! `data:` lines accumulate (joined by `\n`), lines starting with `:` are comments,
+//! and `event:`, `id:` and `retry:` set the event type, last event id and
+//! reconnection delay. Bytes can be fed in arbitrary chunks.
+
+use std::time::Duration;
+
+/// A single dispatched server-sent event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type from the `event:` field; `None` means the default `message`.
+    pub event: Option<String>,
+    /// Concatenated `data:` lines.
+    pub data: String,
+    /// Last event id in effect when the event was dispatched.
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes a chunk of the body and returns every event it completes.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(line) = self.next_line() {
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Id of the last event seen, for resuming with `Last-Event-ID`.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Reconnection delay most recently requested by the server.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Discards any partially received event before reading a resumed stream.
+    ///
+    /// The last event id and reconnection delay carry over.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.event = None;
+        self.data = None;
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        let end = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r')?;
+
+        // A trailing CR may be the first half of a CRLF split across chunks
+        let terminator_len = match (self.buffer[end], self.buffer.get(end + 1)) {
+            (b'\r', Some(b'\n')) => 2,
+            (b'\r', None) => return None,
+            _ => 1,
+        };
+
+        let line = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+        self.buffer.drain(..end + terminator_len);
+        Some(line)
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line.starts_with(':') {
+            return None; // Comment, often used as a keep-alive
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                let data = self.data.get_or_insert_with(String::new);
+                if !data.is_empty() {
+                    data.push('\n');
+                }
+                data.push_str(value);
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse::<u64>() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {} // Unknown fields are ignored
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let data = self.data.take()?;
+
+        Some(SseEvent {
+            event,
+            data,
+            id: self.last_event_id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_fields_and_multiline_data() {
+        let mut parser = SseParser::new();
+        let events = parser.feed(b": keep-alive\nevent: message\nid: 42\nretry: 1500\ndata: {\"a\":\ndata: 1}\n\n");
+
+        assert_eq!(events, vec![SseEvent {
+            event: Some("message".to_string()),
+            data: "{\"a\":\n1}".to_string(),
+            id: Some("42".to_string()),
+        }]);
+        assert_eq!(parser.last_event_id(), Some("42"));
+        assert_eq!(parser.retry(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut parser = SseParser::new();
+
+        assert!(parser.feed(b"data: first\r").is_empty());
+        assert!(parser.feed(b"\n\r").is_empty());
+        let events = parser.feed(b"\ndata:second\n\ndata: partial");
+
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_id_only_event_updates_last_event_id() {
+        let mut parser = SseParser::new();
+
+        // Servers prime resumable streams with an event that carries only an id
+        assert!(parser.feed(b"id: prime\n\n").is_empty());
+        assert_eq!(parser.last_event_id(), Some("prime"));
+    }
+
+    #[test]
+    fn test_reset_keeps_resume_state() {
+        let mut parser = SseParser::new();
+        parser.feed(b"retry: 250\nid: 3\ndata: complete\n\ndata: cut off");
+        parser.reset();
+
+        let events = parser.feed(b"\n\ndata: resumed\n\n");
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, vec!["resumed"]);
+        assert_eq!(parser.last_event_id(), Some("3"));
+        assert_eq!(parser.retry(), Some(Duration::from_millis(250)));
+    }
+}
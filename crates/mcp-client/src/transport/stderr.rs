@@ -0,0 +1,237 @@
+//! Drains a server subprocess's stderr so the child never blocks on a full pipe.
+//!
+//! Each line is re-emitted as an MCP `notifications/message` to the local
+//! client, written to the tracing log, or both, subject to a per-second limit.
+
+use crate::error::ClientError;
+use crate::transport::{forward_inbound, log_notification, InboundSender};
+use mcp_types::LogLevel;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// Where a subprocess's stderr output goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StderrHandling {
+    /// Forward to the local client and write to the log
+    #[default]
+    Both,
+    /// Forward to the local client as `notifications/message`
+    Notify,
+    /// Write to the log only
+    Log,
+    /// Read and discard
+    Discard,
+}
+
+impl StderrHandling {
+    fn notifies(self) -> bool {
+        matches!(self, StderrHandling::Both | StderrHandling::Notify)
+    }
+
+    fn logs(self) -> bool {
+        matches!(self, StderrHandling::Both | StderrHandling::Log)
+    }
+}
+
+impl FromStr for StderrHandling {
+    type Err = ClientError;
+
+    fn from_str(handling: &str) -> crate::error::Result<Self> {
+        match handling.to_lowercase().as_str() {
+            "both" => Ok(StderrHandling::Both),
+            "notify" => Ok(StderrHandling::Notify),
+            "log" => Ok(StderrHandling::Log),
+            "discard" => Ok(StderrHandling::Discard),
+            _ => Err(ClientError::Connection(format!(
+                "Unknown stderr handling '{}': expected both, notify, log or discard",
+                handling
+            ))),
+        }
+    }
+}
+
+/// Admits at most `limit` lines per one-second window; 0 means unlimited.
+struct RateLimiter {
+    limit: u32,
+    window_start: Instant,
+    admitted: u32,
+    suppressed: u64,
+}
+
+impl RateLimiter {
+    fn new(limit: u32) -> Self {
+        Self { limit, window_start: Instant::now(), admitted: 0, suppressed: 0 }
+    }
+
+    /// Returns whether a line arriving at `now` may be emitted, plus the number of
+    /// lines dropped in the previous window once that window has ended.
+    fn admit(&mut self, now: Instant) -> (bool, Option<u64>) {
+        if self.limit == 0 {
+            return (true, None);
+        }
+
+        let mut report = None;
+        if now.duration_since(self.window_start) >= Duration::from_secs(1) {
+            if self.suppressed > 0 {
+                report = Some(self.suppressed);
+            }
+            self.window_start = now;
+            self.admitted = 0;
+            self.suppressed = 0;
+        }
+
+        if self.admitted < self.limit {
+            self.admitted += 1;
+            (true, report)
+        } else {
+            self.suppressed += 1;
+            (false, report)
+        }
+    }
+
+    /// When lines are being dropped, the moment the current window closes.
+    fn window_end(&self) -> Option<Instant> {
+        (self.suppressed > 0).then(|| self.window_start + Duration::from_secs(1))
+    }
+
+    /// Takes the count of lines dropped so far.
+    fn take_suppressed(&mut self) -> Option<u64> {
+        (self.suppressed > 0).then(|| std::mem::take(&mut self.suppressed))
+    }
+}
+
+/// Guesses a level from common prefixes such as `ERROR` or `warning:`.
+fn classify(line: &str) -> LogLevel {
+    let lower = line.trim_start().to_lowercase();
+    let mentions = |prefixes: &[&str]| prefixes.iter().any(|p| lower.starts_with(p) || lower.contains(&format!("[{}", p)));
+
+    if mentions(&["error", "fatal", "panic", "traceback", "exception"]) || lower.contains("panicked at") {
+        LogLevel::Error
+    } else if mentions(&["warn"]) {
+        LogLevel::Warn
+    } else if mentions(&["debug", "trace"]) {
+        LogLevel::Debug
+    } else {
+        LogLevel::Info
+    }
+}
+
+fn emit(level: LogLevel, logger: &str, line: &str, handling: StderrHandling, inbound: Option<&InboundSender>) {
+    if handling.logs() {
+        match level {
+            LogLevel::Error => error!("[{}] {}", logger, line),
+            LogLevel::Warn => warn!("[{}] {}", logger, line),
+            LogLevel::Info => info!("[{}] {}", logger, line),
+            LogLevel::Debug => debug!("[{}] {}", logger, line),
+        }
+    }
+
+    if handling.notifies() {
+        forward_inbound(inbound, &log_notification(level, logger, line));
+    }
+}
+
+fn report_suppressed(limiter: &mut RateLimiter, logger: &str, handling: StderrHandling, inbound: Option<&InboundSender>) {
+    if let Some(count) = limiter.take_suppressed() {
+        let summary = format!("{} stderr lines suppressed by rate limit", count);
+        emit(LogLevel::Warn, logger, &summary, handling, inbound);
+    }
+}
+
+/// Reads `stderr` line by line until EOF, emitting each line under `logger`.
+pub(crate) fn spawn_stderr_drain<R>(
+    stderr: R,
+    logger: String,
+    handling: StderrHandling,
+    lines_per_second: u32,
+    inbound: Option<InboundSender>,
+) -> JoinHandle<()>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stderr);
+        let mut limiter = RateLimiter::new(lines_per_second);
+        let mut buffer = Vec::new();
+
+        loop {
+            // A read interrupted by the report below keeps its partial line
+            if buffer.ends_with(b"\n") {
+                buffer.clear();
+            }
+            let read = reader.read_until(b'\n', &mut buffer);
+
+            // While dropping lines, report them when the window closes rather than
+            // waiting for the next line, which may never come
+            let read = match limiter.window_end() {
+                Some(deadline) => match tokio::time::timeout_at(deadline.into(), read).await {
+                    Ok(read) => read,
+                    Err(_) => {
+                        report_suppressed(&mut limiter, &logger, handling, inbound.as_ref());
+                        continue;
+                    }
+                },
+                None => read.await,
+            };
+
+            match read {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    debug!("Stopped reading stderr of {}: {}", logger, e);
+                    break;
+                }
+            }
+            if handling == StderrHandling::Discard {
+                continue;
+            }
+
+            // Servers are not obliged to write UTF-8 to stderr
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (admitted, suppressed) = limiter.admit(Instant::now());
+            if let Some(count) = suppressed {
+                let summary = format!("{} stderr lines suppressed by rate limit", count);
+                emit(LogLevel::Warn, &logger, &summary, handling, inbound.as_ref());
+            }
+            if admitted {
+                emit(classify(line), &logger, line, handling, inbound.as_ref());
+            }
+        }
+
+        report_suppressed(&mut limiter, &logger, handling, inbound.as_ref());
+        debug!("stderr of {} closed", logger);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_reports_suppressed_lines() {
+        let mut limiter = RateLimiter::new(2);
+        let start = limiter.window_start;
+
+        assert_eq!(limiter.admit(start), (true, None));
+        assert_eq!(limiter.admit(start), (true, None));
+        assert_eq!(limiter.admit(start), (false, None));
+        assert_eq!(limiter.admit(start), (false, None));
+        assert_eq!(limiter.admit(start + Duration::from_secs(1)), (true, Some(2)));
+    }
+
+    #[test]
+    fn test_classify_levels() {
+        assert!(matches!(classify("Error: listen EADDRINUSE"), LogLevel::Error));
+        assert!(matches!(classify("thread 'main' panicked at src/main.rs:3:5"), LogLevel::Error));
+        assert!(matches!(classify("2024-01-01 [WARN] slow request"), LogLevel::Warn));
+        assert!(matches!(classify("Server listening on stdio"), LogLevel::Info));
+    }
+}
@@ -1,141 +1,716 @@
 use crate::error::{ClientError, Result};
-use crate::transport::{McpClientTransport, TransportConfig};
+use crate::transport::framing::write_detached;
+use crate::transport::pending::{spawn_reader, PendingRequests};
+use crate::transport::stderr::{spawn_stderr_drain, StderrHandling};
+use crate::transport::{
+    forward_inbound, log_notification, Framing, Handshake, request_id, InboundSender, McpClientTransport,
+    TransportConfig,
+};
 use async_trait::async_trait;
+use mcp_types::LogLevel;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::BufReader;
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, watch, Mutex};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
-pub struct StdioTransport {
-    config: TransportConfig,
-    child: Mutex<Option<Child>>,
-    connected: Mutex<bool>,
+/// Default cap on stderr lines re-emitted per second.
+const STDERR_LINES_PER_SECOND: u32 = 20;
+
+/// How long a stopping server gets after stdin closes, and again after SIGTERM.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// When and how often a server that exits unexpectedly is restarted.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Restart the server when it exits without being asked to
+    pub enabled: bool,
+    /// Delay before the first restart, doubled for each further restart in the window
+    pub initial_backoff: Duration,
+    /// Longest delay between restarts
+    pub max_backoff: Duration,
+    /// Give up once the server has been restarted this many times within `crash_window`
+    pub max_restarts: u32,
+    pub crash_window: Duration,
 }
 
-impl StdioTransport {
-    pub fn new(config: TransportConfig) -> Self {
+impl Default for RestartPolicy {
+    fn default() -> Self {
         Self {
-            config,
-            child: Mutex::new(None),
-            connected: Mutex::new(false),
+            enabled: true,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: 5,
+            crash_window: Duration::from_secs(60),
         }
     }
+}
+
+impl RestartPolicy {
+    /// No restarts: the transport disconnects when the server exits.
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Default::default() }
+    }
 
-    async fn start_subprocess(&self) -> Result<Child> {
-        // Parse the endpoint as a command
-        // For stdio transport, endpoint should be like "command arg1 arg2"
-        let parts: Vec<&str> = self.config.endpoint.split_whitespace().collect();
-        if parts.is_empty() {
-            return Err(ClientError::Connection("Empty command".to_string()));
+    /// Delay before the `restart`-th restart within the crash window, counting from 1.
+    fn backoff(&self, restart: u32) -> Duration {
+        let factor = 2u32.saturating_pow(restart.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// How the server subprocess is launched, beyond the command line in `endpoint`.
+#[derive(Debug, Clone)]
+pub struct StdioConfig {
+    /// Arguments appended after those parsed from the endpoint
+    pub args: Vec<String>,
+    /// Environment variables set for the subprocess
+    pub env: HashMap<String, String>,
+    /// Working directory for the subprocess; ours when unset
+    pub cwd: Option<PathBuf>,
+    /// Start from an empty environment instead of inheriting ours.
+    /// `PATH` is then only set if given in `env`.
+    pub clear_env: bool,
+    /// Where the subprocess's stderr lines go
+    pub stderr: StderrHandling,
+    /// Most stderr lines re-emitted per second; 0 disables the limit
+    pub stderr_lines_per_second: u32,
+    /// Restarts after unexpected exits
+    pub restart: RestartPolicy,
+}
+
+impl Default for StdioConfig {
+    fn default() -> Self {
+        Self {
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            clear_env: false,
+            stderr: StderrHandling::default(),
+            stderr_lines_per_second: STDERR_LINES_PER_SECOND,
+            restart: RestartPolicy::default(),
         }
+    }
+}
 
-        let mut cmd = Command::new(parts[0]);
-        if parts.len() > 1 {
-            cmd.args(&parts[1..]);
+impl StdioConfig {
+    pub fn with_arg(mut self, arg: String) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub fn with_env(mut self, key: String, value: String) -> Self {
+        self.env.insert(key, value);
+        self
+    }
+
+    pub fn with_cwd(mut self, cwd: PathBuf) -> Self {
+        self.cwd = Some(cwd);
+        self
+    }
+
+    pub fn with_clear_env(mut self, clear_env: bool) -> Self {
+        self.clear_env = clear_env;
+        self
+    }
+
+    pub fn with_stderr(mut self, stderr: StderrHandling, lines_per_second: u32) -> Self {
+        self.stderr = stderr;
+        self.stderr_lines_per_second = lines_per_second;
+        self
+    }
+
+    pub fn with_restart(mut self, restart: RestartPolicy) -> Self {
+        self.restart = restart;
+        self
+    }
+}
+
+/// Splits a command line the way a POSIX shell would, honoring quotes and escapes.
+fn parse_command_line(command_line: &str) -> Result<Vec<String>> {
+    let parts = shlex::split(command_line)
+        .ok_or_else(|| ClientError::Connection(format!("Unbalanced quotes in command '{}'", command_line)))?;
+    if parts.is_empty() {
+        return Err(ClientError::Connection("Empty command".to_string()));
+    }
+    Ok(parts)
+}
+
+/// Names log output after the program, e.g. `npx` for `/usr/bin/npx -y server`.
+fn logger_name(command_line: &str) -> String {
+    let program = shlex::split(command_line)
+        .and_then(|parts| parts.into_iter().next())
+        .unwrap_or_else(|| "server".to_string());
+    std::path::Path::new(&program)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or(program)
+}
+
+/// Lifecycle of the supervised server process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessState {
+    Running,
+    /// Exited unexpectedly; a replacement is being started
+    Restarting,
+    Stopped,
+}
+
+/// A launched server process and the tasks reading its output.
+struct Process {
+    child: Child,
+    reader_task: JoinHandle<()>,
+    stderr_task: Option<JoinHandle<()>>,
+}
+
+impl Process {
+    /// Launches the server and starts draining stdout and stderr.
+    fn spawn(config: &TransportConfig, pending: &Arc<PendingRequests>, inbound: &Option<InboundSender>) -> Result<(Self, ChildStdin)> {
+        // For stdio transport, endpoint is a command line like `npx -y server --root "/my docs"`
+        let parts = parse_command_line(&config.endpoint)?;
+        let options = &config.stdio;
+
+        let mut cmd = Command::new(&parts[0]);
+        cmd.args(&parts[1..]).args(&options.args);
+
+        if options.clear_env {
+            cmd.env_clear();
+        }
+        cmd.envs(&options.env);
+        if let Some(cwd) = &options.cwd {
+            cmd.current_dir(cwd);
         }
 
-        let child = cmd
+        let mut child = cmd
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| ClientError::Connection(format!("Failed to start subprocess: {}", e)))?;
+            .map_err(|e| ClientError::Connection(format!("Failed to start subprocess '{}': {}", parts[0], e)))?;
+
+        info!("Started MCP server subprocess: {}", config.endpoint);
+
+        let stdin = child.stdin.take()
+            .ok_or_else(|| ClientError::Connection("No stdin available".to_string()))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| ClientError::Connection("No stdout available".to_string()))?;
+
+        // The supervisor tracks liveness through the child itself, not the stream
+        let reader_task = spawn_reader(
+            BufReader::new(stdout),
+            config.framing,
+            pending.clone(),
+            inbound.clone(),
+            Arc::new(Mutex::new(true)),
+        );
+
+        // Always drained, even when discarded, so a chatty server cannot fill the pipe and stall
+        let stderr_task = child.stderr.take().map(|stderr| {
+            spawn_stderr_drain(
+                stderr,
+                logger_name(&config.endpoint),
+                options.stderr,
+                options.stderr_lines_per_second,
+                inbound.clone(),
+            )
+        });
+
+        Ok((Self { child, reader_task, stderr_task }, stdin))
+    }
 
-        info!("Started MCP server subprocess: {}", self.config.endpoint);
-        Ok(child)
+    /// Stops the process: stdin is expected to be closed already, then SIGTERM, then SIGKILL.
+    async fn terminate(mut self) {
+        match tokio::time::timeout(SHUTDOWN_GRACE, self.child.wait()).await {
+            Ok(Ok(status)) => info!("Subprocess exited with status: {}", status),
+            Ok(Err(e)) => error!("Error waiting for subprocess: {}", e),
+            Err(_) => {
+                #[cfg(unix)]
+                if let Some(pid) = self.child.id() {
+                    warn!("Subprocess did not exit after closing stdin, sending SIGTERM");
+                    // SAFETY: `pid` belongs to our child, which has not been reaped yet
+                    unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+                    if let Ok(Ok(status)) = tokio::time::timeout(SHUTDOWN_GRACE, self.child.wait()).await {
+                        info!("Subprocess exited with status: {}", status);
+                        return self.stop_tasks();
+                    }
+                }
+
+                warn!("Subprocess did not exit within timeout, killing it");
+                let _ = self.child.kill().await;
+            }
+        }
+        self.stop_tasks();
+    }
+
+    fn stop_tasks(self) {
+        self.reader_task.abort();
+        if let Some(stderr_task) = self.stderr_task {
+            stderr_task.abort();
+        }
+    }
+}
+
+async fn write_frame(stdin: &Arc<Mutex<Option<ChildStdin>>>, framing: Framing, message: &str) -> Result<()> {
+    write_detached(stdin, framing, message, "No stdin available").await
+}
+
+/// Watches the server process and replaces it when it dies.
+struct Supervisor {
+    config: TransportConfig,
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    pending: Arc<PendingRequests>,
+    inbound: Option<InboundSender>,
+    state: Arc<watch::Sender<ProcessState>>,
+    handshake: Arc<Mutex<Handshake>>,
+}
+
+impl Supervisor {
+    async fn run(self, mut process: Process, mut shutdown: oneshot::Receiver<()>) {
+        let policy = self.config.stdio.restart.clone();
+        let mut restarts: VecDeque<Instant> = VecDeque::new();
+
+        loop {
+            let status = tokio::select! {
+                status = process.child.wait() => status,
+                _ = &mut shutdown => {
+                    drop(self.stdin.lock().await.take());
+                    process.terminate().await;
+                    self.stop().await;
+                    return;
+                }
+            };
+
+            let status = match status {
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            if !policy.enabled {
+                self.report(LogLevel::Error, &format!("STDIO server exited ({})", status));
+                self.stop().await;
+                return;
+            }
+
+            // New requests now wait for the replacement; anything written to the dead
+            // process fails. stderr keeps draining on its own so crash output is not lost
+            self.state.send_replace(ProcessState::Restarting);
+            drop(self.stdin.lock().await.take());
+            self.pending.fail_all();
+            let stale_reader = process.reader_task;
+
+            loop {
+                let now = Instant::now();
+                while restarts.front().is_some_and(|at| now.duration_since(*at) > policy.crash_window) {
+                    restarts.pop_front();
+                }
+                if restarts.len() >= policy.max_restarts as usize {
+                    self.report(LogLevel::Error, &format!(
+                        "STDIO server exited ({}) and was restarted {} times within {:?}; giving up",
+                        status, restarts.len(), policy.crash_window
+                    ));
+                    self.stop().await;
+                    return;
+                }
+
+                restarts.push_back(now);
+                let delay = policy.backoff(restarts.len() as u32);
+                self.report(LogLevel::Warn, &format!(
+                    "STDIO server exited ({}); restarting in {:?} (restart {} of {} allowed within {:?})",
+                    status, delay, restarts.len(), policy.max_restarts, policy.crash_window
+                ));
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = &mut shutdown => {
+                        self.stop().await;
+                        return;
+                    }
+                }
+
+                // Anything still unread belongs to the dead process and must not
+                // fail requests sent to its replacement
+                stale_reader.abort();
+
+                match self.relaunch(restarts.len()).await {
+                    Ok(replacement) => {
+                        process = replacement;
+                        break;
+                    }
+                    Err(e) => warn!("Restarting STDIO server failed: {}", e),
+                }
+            }
+
+            self.state.send_replace(ProcessState::Running);
+            self.report(LogLevel::Info, "STDIO server restarted");
+        }
+    }
+
+    /// Starts a replacement process and repeats the client's handshake with it.
+    async fn relaunch(&self, restart: usize) -> Result<Process> {
+        let (process, stdin) = Process::spawn(&self.config, &self.pending, &self.inbound)?;
+        *self.stdin.lock().await = Some(stdin);
+
+        match self.replay_handshake(restart).await {
+            Ok(()) => Ok(process),
+            Err(e) => {
+                drop(self.stdin.lock().await.take());
+                process.terminate().await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn replay_handshake(&self, restart: usize) -> Result<()> {
+        let handshake = self.handshake.lock().await.clone();
+        let Some(mut initialize) = handshake.initialize else {
+            return Ok(()); // The client has not initialized yet
+        };
+
+        // A private id, so the reply cannot be mistaken for one the client awaits
+        let id = json!(format!("mcp-connect-restart-{}", restart));
+        initialize["id"] = id.clone();
+
+        let slot = self.pending.register(&id);
+        if let Err(e) = write_frame(&self.stdin, self.config.framing, &initialize.to_string()).await {
+            self.pending.remove(&id);
+            return Err(e);
+        }
+        let response = self.pending.wait(&id, slot, self.config.timeout).await?;
+        if serde_json::from_str::<Value>(&response).is_ok_and(|r| r.get("error").is_some()) {
+            return Err(ClientError::Protocol(format!("Restarted server rejected initialize: {}", response)));
+        }
+
+        if let Some(initialized) = handshake.initialized {
+            write_frame(&self.stdin, self.config.framing, &initialized).await?;
+        }
+        debug!("Replayed initialize handshake after restart");
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        drop(self.stdin.lock().await.take());
+        self.state.send_replace(ProcessState::Stopped);
+        self.pending.fail_all();
+    }
+
+    /// Logs a lifecycle event and tells the local client about it.
+    fn report(&self, level: LogLevel, message: &str) {
+        match level {
+            LogLevel::Error => error!("{}", message),
+            LogLevel::Warn => warn!("{}", message),
+            _ => info!("{}", message),
+        }
+        forward_inbound(self.inbound.as_ref(), &log_notification(level, "mcp-connect", message));
+    }
+}
+
+/// Runs an MCP server as a subprocess and exchanges messages over its stdio,
+/// framed as `config.framing` says: newline-delimited JSON by default, or
+/// `Content-Length` headers or length prefixes (see [`Framing`]).
+///
+/// A supervisor task restarts the server if it exits unexpectedly, according to
+/// the configured [`RestartPolicy`].
+pub struct StdioTransport {
+    config: TransportConfig,
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    supervisor: Mutex<Option<(JoinHandle<()>, oneshot::Sender<()>)>>,
+    pending: Arc<PendingRequests>,
+    state: Arc<watch::Sender<ProcessState>>,
+    handshake: Arc<Mutex<Handshake>>,
+    inbound: Option<InboundSender>,
+}
+
+impl StdioTransport {
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            stdin: Arc::new(Mutex::new(None)),
+            supervisor: Mutex::new(None),
+            pending: Arc::new(PendingRequests::new()),
+            state: Arc::new(watch::Sender::new(ProcessState::Stopped)),
+            handshake: Arc::new(Mutex::new(Handshake::default())),
+            inbound: None,
+        }
+    }
+
+    /// Waits out a restart in progress, up to the request timeout.
+    async fn wait_until_running(&self) -> Result<()> {
+        let mut state = self.state.subscribe();
+        let settled = tokio::time::timeout(
+            self.config.timeout,
+            state.wait_for(|state| *state != ProcessState::Restarting),
+        )
+        .await
+        .map_err(|_| ClientError::Timeout)?;
+
+        match settled.map(|state| *state) {
+            Ok(ProcessState::Running) => Ok(()),
+            _ => Err(ClientError::Connection("Not connected".to_string())),
+        }
+    }
+
+    async fn write_frame(&self, message: &str) -> Result<()> {
+        self.wait_until_running().await?;
+        write_frame(&self.stdin, self.config.framing, message).await
+    }
+
+    /// Remembers the client's `initialize` and `initialized` so a restart can repeat them.
+    async fn record_handshake(&self, message: &str) {
+        self.handshake.lock().await.record(message);
     }
 }
 
 #[async_trait]
 impl McpClientTransport for StdioTransport {
+    fn set_inbound_sender(&mut self, sender: InboundSender) {
+        self.inbound = Some(sender);
+    }
+
     async fn connect(&mut self) -> Result<()> {
         info!("Connecting to MCP server via STDIO: {}", self.config.endpoint);
 
-        let child = self.start_subprocess().await?;
-        *self.child.lock().await = Some(child);
-        *self.connected.lock().await = true;
+        let (process, stdin) = Process::spawn(&self.config, &self.pending, &self.inbound)?;
+        *self.stdin.lock().await = Some(stdin);
+        self.state.send_replace(ProcessState::Running);
+
+        let supervisor = Supervisor {
+            config: self.config.clone(),
+            stdin: self.stdin.clone(),
+            pending: self.pending.clone(),
+            inbound: self.inbound.clone(),
+            state: self.state.clone(),
+            handshake: self.handshake.clone(),
+        };
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let task = tokio::spawn(supervisor.run(process, shutdown_rx));
+        *self.supervisor.lock().await = Some((task, shutdown_tx));
 
         info!("Successfully connected to MCP server via STDIO");
         Ok(())
     }
 
-    async fn send_request(&mut self, request: &str) -> Result<String> {
+    async fn send_request(&self, request: &str) -> Result<String> {
         if !self.is_connected().await {
             return Err(ClientError::Connection("Not connected".to_string()));
         }
 
-        let mut child_guard = self.child.lock().await;
-        let child = child_guard.as_mut()
-            .ok_or_else(|| ClientError::Connection("No active subprocess".to_string()))?;
-
-        let stdin = child.stdin.as_mut()
-            .ok_or_else(|| ClientError::Connection("No stdin available".to_string()))?;
-
-        let stdout = child.stdout.as_mut()
-            .ok_or_else(|| ClientError::Connection("No stdout available".to_string()))?;
+        let id = request_id(request)
+            .ok_or_else(|| ClientError::Protocol("Request has no id".to_string()))?;
 
         debug!("Sending request: {}", request);
+        self.record_handshake(request).await;
+        self.wait_until_running().await?;
 
-        // Send the request
-        stdin.write_all(request.as_bytes()).await?;
-        stdin.write_all(b"\n").await?;
-        stdin.flush().await?;
-
-        // Read the response
-        let mut reader = BufReader::new(stdout);
-        let mut response = String::new();
-
-        match tokio::time::timeout(self.config.timeout, reader.read_line(&mut response)).await {
-            Ok(Ok(0)) => {
-                error!("Subprocess closed stdout");
-                *self.connected.lock().await = false;
-                Err(ClientError::Connection("Subprocess closed".to_string()))
-            }
-            Ok(Ok(_)) => {
-                let response = response.trim().to_string();
-                debug!("Received response: {}", response);
-                Ok(response)
-            }
-            Ok(Err(e)) => {
-                error!("IO error reading response: {}", e);
-                Err(ClientError::Io(e))
-            }
-            Err(_) => {
-                error!("Timeout waiting for response");
-                Err(ClientError::Timeout)
-            }
+        // Register before writing so a fast reply can't slip past us
+        let slot = self.pending.register(&id);
+        if let Err(e) = write_frame(&self.stdin, self.config.framing, request).await {
+            self.pending.remove(&id);
+            return Err(e);
         }
+
+        // If the server dies with the request in flight, resending could repeat side
+        // effects, so the request fails as interrupted even while it is being replaced
+        self.pending.wait(&id, slot, self.config.timeout).await
     }
 
-    async fn disconnect(&mut self) -> Result<()> {
-        *self.connected.lock().await = false;
+    async fn send_message(&self, message: &str) -> Result<()> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
 
-        let mut child_guard = self.child.lock().await;
-        if let Some(mut child) = child_guard.take() {
-            // Close stdin to signal the subprocess to exit
-            drop(child.stdin.take());
+        debug!("Sending message: {}", message);
+        self.record_handshake(message).await;
+        self.write_frame(message).await
+    }
 
-            // Wait for the subprocess to exit or kill it after a timeout
-            match tokio::time::timeout(std::time::Duration::from_secs(5), child.wait()).await {
-                Ok(Ok(status)) => {
-                    info!("Subprocess exited with status: {}", status);
-                }
-                Ok(Err(e)) => {
-                    error!("Error waiting for subprocess: {}", e);
-                }
-                Err(_) => {
-                    warn!("Subprocess did not exit within timeout, killing it");
-                    let _ = child.kill().await;
-                }
-            }
+    async fn disconnect(&self) -> Result<()> {
+        if let Some((task, shutdown)) = self.supervisor.lock().await.take() {
+            // The supervisor closes stdin and escalates to SIGTERM and SIGKILL as needed
+            let _ = shutdown.send(());
+            let _ = task.await;
         }
+        self.state.send_replace(ProcessState::Stopped);
+        self.pending.fail_all();
 
         info!("Disconnected from MCP server");
         Ok(())
     }
 
     async fn is_connected(&self) -> bool {
-        *self.connected.lock().await
+        *self.state.borrow() != ProcessState::Stopped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(
+            parse_command_line(r#"npx -y @scope/server --root "/my docs" 'it''s'"#).unwrap(),
+            vec!["npx", "-y", "@scope/server", "--root", "/my docs", "its"]
+        );
+        assert!(parse_command_line("server --name \"unterminated").is_err());
+        assert!(parse_command_line("   ").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stderr_forwarded_as_log_notifications() {
+        let (inbound_tx, mut inbound_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut transport = StdioTransport::new(TransportConfig {
+            endpoint: "/bin/sh -c 'echo \"Error: config missing\" >&2; echo \"WARN: cache cold\" >&2; echo starting >&2; read line'".to_string(),
+            ..Default::default()
+        });
+        transport.set_inbound_sender(inbound_tx);
+        transport.connect().await.unwrap();
+
+        let first: serde_json::Value = serde_json::from_str(&inbound_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(first["method"], "notifications/message");
+        assert_eq!(first["params"]["level"], "error");
+        assert_eq!(first["params"]["logger"], "sh");
+        assert_eq!(first["params"]["data"], "Error: config missing");
+
+        // MCP names the level "warning"
+        let second: serde_json::Value = serde_json::from_str(&inbound_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(second["params"]["level"], "warning");
+
+        let third: serde_json::Value = serde_json::from_str(&inbound_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(third["params"]["level"], "info");
+
+        transport.disconnect().await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_subprocess_env_and_cwd() {
+        let cwd = std::env::temp_dir().canonicalize().unwrap();
+        let script = r#"read line; printf '{"jsonrpc":"2.0","id":1,"result":{"cwd":"%s","greeting":"%s","home":"%s"}}\n' "$PWD" "$GREETING" "$HOME""#;
+
+        let mut transport = StdioTransport::new(TransportConfig {
+            endpoint: "/bin/sh -c".to_string(),
+            stdio: StdioConfig::default()
+                .with_arg(script.to_string())
+                .with_env("GREETING".to_string(), "hello world".to_string())
+                .with_cwd(cwd.clone())
+                .with_clear_env(true),
+            ..Default::default()
+        });
+        transport.connect().await.unwrap();
+
+        let response = transport.send_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).await.unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"]["cwd"], cwd.to_str().unwrap());
+        assert_eq!(response["result"]["greeting"], "hello world");
+        assert_eq!(response["result"]["home"], "");
+
+        transport.disconnect().await.unwrap();
+    }
+
+    #[test]
+    fn test_restart_backoff_doubles_up_to_cap() {
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(3),
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(4), Duration::from_secs(3));
+        assert_eq!(policy.backoff(40), Duration::from_secs(3));
+    }
+
+    /// Answers requests with an empty result once initialized, and exits on `crash`.
+    #[cfg(unix)]
+    const FLAKY_SERVER: &str = r#"
+        while read -r line; do
+            case "$line" in *'"id"'*) ;; *) continue ;; esac
+            id=$(printf '%s' "$line" | sed 's/.*"id":\([^,}]*\).*/\1/')
+            case "$line" in
+                *'"crash"'*) exit 1 ;;
+                *'"initialize"'*) ready=1 ;;
+            esac
+            if [ -n "$ready" ]; then
+                printf '{"jsonrpc":"2.0","id":%s,"result":{}}\n' "$id"
+            else
+                printf '{"jsonrpc":"2.0","id":%s,"error":{"code":-32002,"message":"not initialized"}}\n' "$id"
+            fi
+        done"#;
+
+    #[cfg(unix)]
+    fn restart_policy() -> RestartPolicy {
+        RestartPolicy {
+            initial_backoff: Duration::from_millis(10),
+            max_restarts: 2,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restarts_and_replays_initialize() {
+        let (inbound_tx, mut inbound_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut transport = StdioTransport::new(TransportConfig {
+            endpoint: "/bin/sh -c".to_string(),
+            stdio: StdioConfig::default()
+                .with_arg(FLAKY_SERVER.to_string())
+                .with_restart(restart_policy()),
+            ..Default::default()
+        });
+        transport.set_inbound_sender(inbound_tx);
+        transport.connect().await.unwrap();
+
+        transport.send_request(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#).await.unwrap();
+        transport.send_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).await.unwrap();
+
+        let crashed = transport.send_request(r#"{"jsonrpc":"2.0","id":2,"method":"crash"}"#).await;
+        assert!(matches!(crashed, Err(ClientError::RequestInterrupted(_))));
+        assert!(transport.is_connected().await);
+
+        // Lifecycle notifications must be valid MCP log messages
+        let notification = |message: String| -> LoggingMessageNotificationParam {
+            serde_json::from_value(serde_json::from_str::<Value>(&message).unwrap()["params"].take()).unwrap()
+        };
+        let restarting = notification(inbound_rx.recv().await.unwrap());
+        assert_eq!(restarting.level, LoggingLevel::Warning);
+        let restarted = notification(inbound_rx.recv().await.unwrap());
+        assert_eq!(restarted.level, LoggingLevel::Info);
+        assert_eq!(restarted.data, "STDIO server restarted");
+
+        // Only answered if the replacement was initialized behind the scenes
+        let response = transport.send_request(r#"{"jsonrpc":"2.0","id":3,"method":"ping"}"#).await.unwrap();
+        assert!(response.contains(r#""result""#));
+
+        transport.disconnect().await.unwrap();
+        assert!(!transport.is_connected().await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_gives_up_on_crash_loop() {
+        let (inbound_tx, mut inbound_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut transport = StdioTransport::new(TransportConfig {
+            endpoint: "/bin/sh -c 'exit 3'".to_string(),
+            stdio: StdioConfig::default().with_restart(restart_policy()),
+            ..Default::default()
+        });
+        transport.set_inbound_sender(inbound_tx);
+        transport.connect().await.unwrap();
+
+        loop {
+            let notification: Value = serde_json::from_str(&inbound_rx.recv().await.unwrap()).unwrap();
+            if notification["params"]["level"] == "error" {
+                assert!(notification["params"]["data"].as_str().unwrap().contains("giving up"));
+                break;
+            }
+        }
+        assert!(!transport.is_connected().await);
     }
 }
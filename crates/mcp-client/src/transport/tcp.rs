@@ -1,53 +1,127 @@
 use crate::error::{ClientError, Result};
-use crate::transport::{McpClientTransport, TransportConfig};
+use crate::transport::framing::write_detached;
+use crate::transport::pending::{spawn_reader, PendingRequests};
+use crate::transport::{request_id, InboundSender, McpClientTransport, TransportConfig};
 use async_trait::async_trait;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::TcpStream;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::sync::Mutex;
-use tracing::{debug, error, info, warn};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// A parsed TCP endpoint: `host:port`, a bare port, or `tls://host:port`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TcpEndpoint {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+impl std::fmt::Display for TcpEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scheme = if self.tls { "tls://" } else { "" };
+        if self.host.contains(':') {
+            write!(f, "{}[{}]:{}", scheme, self.host, self.port)
+        } else {
+            write!(f, "{}{}:{}", scheme, self.host, self.port)
+        }
+    }
+}
 
 pub struct TcpTransport {
     config: TransportConfig,
-    stream: Arc<Mutex<Option<TcpStream>>>,
+    writer: Arc<Mutex<Option<BoxedWriter>>>,
+    reader_task: Mutex<Option<JoinHandle<()>>>,
+    pending: Arc<PendingRequests>,
     connected: Arc<Mutex<bool>>,
+    inbound: Option<InboundSender>,
 }
 
 impl TcpTransport {
     pub fn new(config: TransportConfig) -> Self {
         Self {
             config,
-            stream: Arc::new(Mutex::new(None)),
+            writer: Arc::new(Mutex::new(None)),
+            reader_task: Mutex::new(None),
+            pending: Arc::new(PendingRequests::new()),
             connected: Arc::new(Mutex::new(false)),
+            inbound: None,
         }
     }
 
-    async fn parse_address(&self) -> Result<std::net::SocketAddr> {
+    fn parse_address(&self) -> Result<TcpEndpoint> {
+        let endpoint = self.config.endpoint.as_str();
+        let (tls, address) = match endpoint.strip_prefix("tls://") {
+            Some(address) => (true, address),
+            None => (false, endpoint.strip_prefix("tcp://").unwrap_or(endpoint)),
+        };
+
         // Parse endpoint as "host:port" or just "port" (defaults to localhost)
-        let addr = if self.config.endpoint.contains(':') {
-            self.config.endpoint.clone()
-        } else {
-            format!("127.0.0.1:{}", self.config.endpoint)
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+            None => ("127.0.0.1", address),
         };
 
-        addr.parse()
-            .map_err(|e| ClientError::Connection(format!("Invalid address '{}': {}", addr, e)))
+        let port = port.parse()
+            .map_err(|e| ClientError::Connection(format!("Invalid address '{}': {}", endpoint, e)))?;
+        if host.is_empty() {
+            return Err(ClientError::Connection(format!("Invalid address '{}': missing host", endpoint)));
+        }
+
+        Ok(TcpEndpoint { host: host.to_string(), port, tls })
+    }
+
+    /// Dials the endpoint and, for `tls://`, completes the TLS handshake.
+    async fn open(&self, endpoint: &TcpEndpoint) -> Result<(BoxedReader, BoxedWriter)> {
+        let scheme = if endpoint.tls { "tls" } else { "tcp" };
+        let stream = self.config.proxy.connect(scheme, &endpoint.host, endpoint.port).await?;
+
+        if !endpoint.tls {
+            let (read_half, write_half) = stream.into_split();
+            return Ok((Box::new(read_half), Box::new(write_half)));
+        }
+
+        let stream = self.config.tls.connect(&endpoint.host, stream).await?;
+        let (read_half, write_half) = tokio::io::split(stream);
+        Ok((Box::new(read_half), Box::new(write_half)))
+    }
+
+    async fn write_frame(&self, message: &str) -> Result<()> {
+        write_detached(&self.writer, self.config.framing, message, "No active connection").await
     }
 }
 
 #[async_trait]
 impl McpClientTransport for TcpTransport {
+    fn set_inbound_sender(&mut self, sender: InboundSender) {
+        self.inbound = Some(sender);
+    }
+
     async fn connect(&mut self) -> Result<()> {
         info!("Connecting to MCP server via TCP: {}", self.config.endpoint);
 
-        let addr = self.parse_address().await?;
+        let addr = self.parse_address()?;
+        self.config.proxy.validate()?;
+        if !addr.tls && self.config.tls.is_customized() {
+            warn!("TLS options are ignored for plaintext endpoint {}; use tls://", addr);
+        }
 
         for attempt in 1..=self.config.retry_attempts {
-            match TcpStream::connect(addr).await {
-                Ok(stream) => {
-                    *self.stream.lock().await = Some(stream);
+            match self.open(&addr).await {
+                Ok((read_half, write_half)) => {
+                    *self.writer.lock().await = Some(write_half);
                     *self.connected.lock().await = true;
-                    info!("Successfully connected to MCP server via TCP");
+                    *self.reader_task.lock().await = Some(spawn_reader(
+                        BufReader::new(read_half),
+                        self.config.framing,
+                        self.pending.clone(),
+                        self.inbound.clone(),
+                        self.connected.clone(),
+                    ));
+                    info!("Successfully connected to MCP server via TCP{}", if addr.tls { " with TLS" } else { "" });
                     return Ok(());
                 }
                 Err(e) => {
@@ -65,57 +139,49 @@ impl McpClientTransport for TcpTransport {
         )))
     }
 
-    async fn send_request(&mut self, request: &str) -> Result<String> {
+    async fn send_request(&self, request: &str) -> Result<String> {
         if !self.is_connected().await {
             return Err(ClientError::Connection("Not connected".to_string()));
         }
 
-        let mut stream_guard = self.stream.lock().await;
-        let stream = stream_guard.as_mut()
-            .ok_or_else(|| ClientError::Connection("No active connection".to_string()))?;
+        let id = request_id(request)
+            .ok_or_else(|| ClientError::Protocol("Request has no id".to_string()))?;
 
         debug!("Sending request: {}", request);
 
-        // Send the request
-        stream.write_all(request.as_bytes()).await?;
-        stream.write_all(b"\n").await?;
-        stream.flush().await?;
+        // Register before writing so a fast reply can't slip past us
+        let slot = self.pending.register(&id);
+        if let Err(e) = self.write_frame(request).await {
+            self.pending.remove(&id);
+            return Err(e);
+        }
 
-        // Read the response
-        let mut reader = BufReader::new(stream);
-        let mut response = String::new();
+        self.pending.wait(&id, slot, self.config.timeout).await
+    }
 
-        match tokio::time::timeout(self.config.timeout, reader.read_line(&mut response)).await {
-            Ok(Ok(0)) => {
-                error!("Connection closed by server");
-                *self.connected.lock().await = false;
-                Err(ClientError::Connection("Connection closed".to_string()))
-            }
-            Ok(Ok(_)) => {
-                let response = response.trim().to_string();
-                debug!("Received response: {}", response);
-                Ok(response)
-            }
-            Ok(Err(e)) => {
-                error!("IO error reading response: {}", e);
-                *self.connected.lock().await = false;
-                Err(ClientError::Io(e))
-            }
-            Err(_) => {
-                error!("Timeout waiting for response");
-                Err(ClientError::Timeout)
-            }
+    async fn send_message(&self, message: &str) -> Result<()> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
         }
+
+        debug!("Sending message: {}", message);
+        self.write_frame(message).await
     }
 
-    async fn disconnect(&mut self) -> Result<()> {
+    async fn disconnect(&self) -> Result<()> {
         *self.connected.lock().await = false;
 
-        let mut stream_guard = self.stream.lock().await;
-        if let Some(mut stream) = stream_guard.take() {
-            let _ = stream.shutdown().await;
+        if let Some(reader_task) = self.reader_task.lock().await.take() {
+            reader_task.abort();
         }
 
+        let mut writer_guard = self.writer.lock().await;
+        if let Some(mut writer) = writer_guard.take() {
+            let _ = writer.shutdown().await;
+        }
+
+        self.pending.fail_all();
+
         info!("Disconnected from MCP server");
         Ok(())
     }
@@ -124,3 +190,31 @@ impl McpClientTransport for TcpTransport {
         *self.connected.lock().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(endpoint: &str) -> Result<TcpEndpoint> {
+        TcpTransport::new(TransportConfig {
+            endpoint: endpoint.to_string(),
+            ..Default::default()
+        })
+        .parse_address()
+    }
+
+    #[test]
+    fn test_parse_address() {
+        let plain = |host: &str, port| TcpEndpoint { host: host.to_string(), port, tls: false };
+
+        assert_eq!(endpoint("8080").unwrap(), plain("127.0.0.1", 8080));
+        assert_eq!(endpoint("localhost:9090").unwrap(), plain("localhost", 9090));
+        assert_eq!(endpoint("[::1]:9090").unwrap(), plain("::1", 9090));
+        assert_eq!(
+            endpoint("tls://mcp.internal:8443").unwrap(),
+            TcpEndpoint { host: "mcp.internal".to_string(), port: 8443, tls: true }
+        );
+        assert!(endpoint("tls://:8443").is_err());
+        assert!(endpoint("localhost:http").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! TLS client settings shared by the transports that can encrypt their connection.
+//!
+//! Trust starts from the system root store, optionally extended with a PEM CA
+//! bundle. A client certificate (PEM or PKCS#12) enables mutual TLS, and
+//! public-key pins restrict which server keys are accepted on top of normal
+//! chain verification.
+
+use crate::error::{ClientError, Result};
+use base64::{engine::general_purpose, Engine as _};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName, SupportedProtocolVersion};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tracing::{debug, warn};
+
+/// Lowest TLS protocol version a connection may negotiate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+
+impl FromStr for TlsVersion {
+    type Err = ClientError;
+
+    fn from_str(version: &str) -> Result<Self> {
+        match version.trim_start_matches("TLS").trim_start_matches("tls").trim() {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            _ => Err(ClientError::Tls(format!("Unsupported TLS version '{}': expected 1.2 or 1.3", version))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// PEM bundle of additional root certificates to trust
+    pub ca_cert: Option<PathBuf>,
+    /// PEM certificate chain presented to the server for mutual TLS
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key for `client_cert`
+    pub client_key: Option<PathBuf>,
+    /// PKCS#12 bundle with the client certificate chain and key, instead of PEM files
+    pub client_pkcs12: Option<PathBuf>,
+    /// Password protecting `client_pkcs12`
+    pub client_pkcs12_password: Option<String>,
+    /// Lowest protocol version to accept; TLS 1.2 when unset
+    pub min_version: Option<TlsVersion>,
+    /// Name used for SNI and certificate verification instead of the endpoint host.
+    /// HTTP transports always use the host from the URL.
+    pub server_name: Option<String>,
+    /// SHA-256 hashes of acceptable server public keys (SubjectPublicKeyInfo),
+    /// base64-encoded with an optional `sha256//` prefix. Empty disables pinning.
+    pub pinned_keys: Vec<String>,
+}
+
+impl TlsConfig {
+    pub fn with_ca_cert(mut self, path: PathBuf) -> Self {
+        self.ca_cert = Some(path);
+        self
+    }
+
+    pub fn with_client_identity(mut self, cert: PathBuf, key: PathBuf) -> Self {
+        self.client_cert = Some(cert);
+        self.client_key = Some(key);
+        self
+    }
+
+    pub fn with_client_pkcs12(mut self, bundle: PathBuf, password: Option<String>) -> Self {
+        self.client_pkcs12 = Some(bundle);
+        self.client_pkcs12_password = password;
+        self
+    }
+
+    pub fn with_min_version(mut self, version: TlsVersion) -> Self {
+        self.min_version = Some(version);
+        self
+    }
+
+    pub fn with_server_name(mut self, server_name: String) -> Self {
+        self.server_name = Some(server_name);
+        self
+    }
+
+    pub fn with_pinned_key(mut self, pin: String) -> Self {
+        self.pinned_keys.push(pin);
+        self
+    }
+
+    /// True if any option differs from plain system-trust TLS.
+    pub fn is_customized(&self) -> bool {
+        self.ca_cert.is_some()
+            || self.client_cert.is_some()
+            || self.client_key.is_some()
+            || self.client_pkcs12.is_some()
+            || self.min_version.is_some()
+            || self.server_name.is_some()
+            || !self.pinned_keys.is_empty()
+    }
+
+    /// Builds the rustls configuration these settings describe.
+    pub(crate) fn client_config(&self) -> Result<ClientConfig> {
+        let identity = match (&self.client_cert, &self.client_key, &self.client_pkcs12) {
+            (Some(cert), Some(key), None) => Some((load_certs(cert)?, load_key(key)?)),
+            (None, None, Some(bundle)) => Some(load_pkcs12(bundle, self.client_pkcs12_password.as_deref())?),
+            (None, None, None) => None,
+            (_, _, Some(_)) => {
+                return Err(ClientError::Tls("Use either a PEM client certificate or a PKCS#12 bundle, not both".to_string()));
+            }
+            _ => return Err(ClientError::Tls("A client certificate and key must be given together".to_string())),
+        };
+
+        let roots = self.root_store()?;
+        let chain_verifier = WebPkiVerifier::new(roots, None);
+        let verifier: Arc<dyn ServerCertVerifier> = if self.pinned_keys.is_empty() {
+            Arc::new(chain_verifier)
+        } else {
+            let pins = self.pinned_keys.iter()
+                .map(|pin| parse_pin(pin))
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(PinnedKeyVerifier { inner: chain_verifier, pins })
+        };
+
+        let versions: &[&SupportedProtocolVersion] = match self.min_version {
+            Some(TlsVersion::Tls13) => &[&rustls::version::TLS13],
+            Some(TlsVersion::Tls12) | None => rustls::DEFAULT_VERSIONS,
+        };
+
+        let builder = ClientConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(versions)
+            .map_err(|e| ClientError::Tls(format!("Invalid TLS versions: {}", e)))?
+            .with_custom_certificate_verifier(verifier);
+
+        match identity {
+            Some((certs, key)) => builder
+                .with_client_auth_cert(certs, key)
+                .map_err(|e| ClientError::Tls(format!("Invalid client certificate: {}", e))),
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+
+    /// Resolves the name to present via SNI and verify the certificate against.
+    pub(crate) fn server_name_for(&self, host: &str) -> Result<ServerName> {
+        let name = self.server_name.as_deref().unwrap_or(host);
+        ServerName::try_from(name)
+            .map_err(|_| ClientError::Tls(format!("Invalid TLS server name '{}'", name)))
+    }
+
+    /// Performs the TLS handshake over an established TCP connection.
+    pub(crate) async fn connect(&self, host: &str, stream: TcpStream) -> Result<TlsStream<TcpStream>> {
+        let connector = TlsConnector::from(Arc::new(self.client_config()?));
+        let server_name = self.server_name_for(host)?;
+
+        connector.connect(server_name, stream).await
+            .map_err(|e| ClientError::Tls(format!("TLS handshake with {} failed: {}", host, e)))
+    }
+
+    fn root_store(&self) -> Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+
+        match rustls_native_certs::load_native_certs() {
+            Ok(certs) => {
+                for cert in certs {
+                    if let Err(e) = roots.add(&Certificate(cert.0)) {
+                        debug!("Skipping unusable system root certificate: {}", e);
+                    }
+                }
+            }
+            Err(e) => warn!("Could not load system root certificates: {}", e),
+        }
+
+        if let Some(ca_cert) = &self.ca_cert {
+            for cert in load_certs(ca_cert)? {
+                roots.add(&cert)
+                    .map_err(|e| ClientError::Tls(format!("Invalid CA certificate in {}: {}", ca_cert.display(), e)))?;
+            }
+        }
+
+        if roots.is_empty() {
+            return Err(ClientError::Tls("No trusted root certificates available".to_string()));
+        }
+        Ok(roots)
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader)?;
+    if certs.is_empty() {
+        return Err(ClientError::Tls(format!("No certificates found in {}", path.display())));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &Path) -> Result<PrivateKey> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    for item in rustls_pemfile::read_all(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => continue,
+        }
+    }
+    Err(ClientError::Tls(format!("No private key found in {}", path.display())))
+}
+
+fn load_pkcs12(path: &Path, password: Option<&str>) -> Result<(Vec<Certificate>, PrivateKey)> {
+    let keystore = p12_keystore::KeyStore::from_pkcs12(&std::fs::read(path)?, password.unwrap_or(""))
+        .map_err(|e| ClientError::Tls(format!("Failed to read PKCS#12 bundle {}: {}", path.display(), e)))?;
+
+    let (_, chain) = keystore.private_key_chain()
+        .ok_or_else(|| ClientError::Tls(format!("No private key found in {}", path.display())))?;
+
+    let certs = chain.chain().iter().map(|cert| Certificate(cert.as_der().to_vec())).collect();
+    Ok((certs, PrivateKey(chain.key().to_vec())))
+}
+
+/// Decodes a `sha256//<base64>` (or bare base64) public-key pin.
+fn parse_pin(pin: &str) -> Result<[u8; 32]> {
+    let encoded = pin.strip_prefix("sha256//")
+        .or_else(|| pin.strip_prefix("sha256/"))
+        .unwrap_or(pin);
+
+    general_purpose::STANDARD.decode(encoded)
+        .ok()
+        .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+        .ok_or_else(|| ClientError::Tls(format!("Invalid public key pin '{}': expected a base64 SHA-256 hash", pin)))
+}
+
+/// SHA-256 of a certificate's DER-encoded SubjectPublicKeyInfo.
+fn spki_hash(cert: &Certificate) -> Option<[u8; 32]> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(&cert.0).ok()?;
+    Some(Sha256::digest(parsed.tbs_certificate.subject_pki.raw).into())
+}
+
+/// Verifies the chain as usual, then requires a pinned key somewhere in it.
+struct PinnedKeyVerifier {
+    inner: WebPkiVerifier,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedKeyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity, intermediates, server_name, scts, ocsp_response, now,
+        )?;
+
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(spki_hash)
+            .any(|hash| self.pins.contains(&hash));
+
+        if pinned {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General("Server public key does not match any pin".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pin() {
+        let hash = [7u8; 32];
+        let encoded = general_purpose::STANDARD.encode(hash);
+
+        assert_eq!(parse_pin(&format!("sha256//{}", encoded)).unwrap(), hash);
+        assert_eq!(parse_pin(&encoded).unwrap(), hash);
+        assert!(parse_pin("sha256//c2hvcnQ=").is_err());
+    }
+
+    #[test]
+    fn test_parse_tls_version() {
+        assert_eq!("1.2".parse::<TlsVersion>().unwrap(), TlsVersion::Tls12);
+        assert_eq!("TLS1.3".parse::<TlsVersion>().unwrap(), TlsVersion::Tls13);
+        assert!("1.1".parse::<TlsVersion>().is_err());
+    }
+
+    #[test]
+    fn test_client_identity_requires_cert_and_key() {
+        let config = TlsConfig {
+            client_cert: Some(PathBuf::from("client.pem")),
+            ..Default::default()
+        };
+        assert!(matches!(config.client_config(), Err(ClientError::Tls(_))));
+    }
+}
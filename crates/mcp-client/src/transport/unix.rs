@@ -0,0 +1,248 @@
+use crate::error::{ClientError, Result};
+use crate::transport::framing::write_detached;
+use crate::transport::pending::{spawn_reader, PendingRequests};
+use crate::transport::{request_id, InboundSender, McpClientTransport, TransportConfig};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::UnixStream;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Where a Unix domain socket lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    /// A socket file on the filesystem.
+    Path(PathBuf),
+    /// A Linux abstract-namespace socket, written `@name`.
+    Abstract(String),
+}
+
+impl SocketAddress {
+    /// Parses `unix:/path`, `unix:///path`, `unix:@name` or a bare path.
+    pub fn parse(endpoint: &str) -> Result<Self> {
+        let address = endpoint.strip_prefix("unix://")
+            .or_else(|| endpoint.strip_prefix("unix:"))
+            .unwrap_or(endpoint);
+
+        if address.is_empty() {
+            return Err(ClientError::Connection(format!("Invalid Unix socket address '{}'", endpoint)));
+        }
+
+        Ok(match address.strip_prefix('@') {
+            Some(name) => SocketAddress::Abstract(name.to_string()),
+            None => SocketAddress::Path(PathBuf::from(address)),
+        })
+    }
+
+    async fn connect(&self) -> std::io::Result<UnixStream> {
+        match self {
+            SocketAddress::Path(path) => UnixStream::connect(path).await,
+            SocketAddress::Abstract(name) => connect_abstract(name),
+        }
+    }
+}
+
+impl std::fmt::Display for SocketAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SocketAddress::Path(path) => write!(f, "{}", path.display()),
+            SocketAddress::Abstract(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &str) -> std::io::Result<UnixStream> {
+    use std::os::linux::net::SocketAddrExt;
+
+    // Local connects complete immediately, so the blocking std call is fine here
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+    let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+    stream.set_nonblocking(true)?;
+    UnixStream::from_std(stream)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn connect_abstract(_name: &str) -> std::io::Result<UnixStream> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "abstract-namespace sockets are only available on Linux",
+    ))
+}
+
+/// JSON-RPC over a Unix domain socket, framed per [`TransportConfig::framing`].
+pub struct UnixTransport {
+    config: TransportConfig,
+    writer: Arc<Mutex<Option<OwnedWriteHalf>>>,
+    reader_task: Mutex<Option<JoinHandle<()>>>,
+    pending: Arc<PendingRequests>,
+    connected: Arc<Mutex<bool>>,
+    inbound: Option<InboundSender>,
+}
+
+impl UnixTransport {
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            writer: Arc::new(Mutex::new(None)),
+            reader_task: Mutex::new(None),
+            pending: Arc::new(PendingRequests::new()),
+            connected: Arc::new(Mutex::new(false)),
+            inbound: None,
+        }
+    }
+
+    async fn write_frame(&self, message: &str) -> Result<()> {
+        write_detached(&self.writer, self.config.framing, message, "No active connection").await
+    }
+}
+
+#[async_trait]
+impl McpClientTransport for UnixTransport {
+    fn set_inbound_sender(&mut self, sender: InboundSender) {
+        self.inbound = Some(sender);
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        info!("Connecting to MCP server via Unix socket: {}", self.config.endpoint);
+
+        let address = SocketAddress::parse(&self.config.endpoint)?;
+
+        for attempt in 1..=self.config.retry_attempts {
+            match address.connect().await {
+                Ok(stream) => {
+                    let (read_half, write_half) = stream.into_split();
+                    *self.writer.lock().await = Some(write_half);
+                    *self.connected.lock().await = true;
+                    *self.reader_task.lock().await = Some(spawn_reader(
+                        BufReader::new(read_half),
+                        self.config.framing,
+                        self.pending.clone(),
+                        self.inbound.clone(),
+                        self.connected.clone(),
+                    ));
+                    info!("Successfully connected to MCP server via Unix socket");
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Connection attempt {} failed: {}", attempt, e);
+                    if attempt < self.config.retry_attempts {
+                        tokio::time::sleep(self.config.retry_delay).await;
+                    }
+                }
+            }
+        }
+
+        Err(ClientError::Connection(format!(
+            "Failed to connect to {} after {} attempts",
+            address, self.config.retry_attempts
+        )))
+    }
+
+    async fn send_request(&self, request: &str) -> Result<String> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        let id = request_id(request)
+            .ok_or_else(|| ClientError::Protocol("Request has no id".to_string()))?;
+
+        debug!("Sending request: {}", request);
+
+        // Register before writing so a fast reply can't slip past us
+        let slot = self.pending.register(&id);
+        if let Err(e) = self.write_frame(request).await {
+            self.pending.remove(&id);
+            return Err(e);
+        }
+
+        self.pending.wait(&id, slot, self.config.timeout).await
+    }
+
+    async fn send_message(&self, message: &str) -> Result<()> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        debug!("Sending message: {}", message);
+        self.write_frame(message).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        *self.connected.lock().await = false;
+
+        if let Some(reader_task) = self.reader_task.lock().await.take() {
+            reader_task.abort();
+        }
+
+        let mut writer_guard = self.writer.lock().await;
+        if let Some(mut writer) = writer_guard.take() {
+            let _ = writer.shutdown().await;
+        }
+
+        self.pending.fail_all();
+
+        info!("Disconnected from MCP server");
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> bool {
+        *self.connected.lock().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_parse_socket_address() {
+        let path = SocketAddress::Path(PathBuf::from("/run/mcp.sock"));
+        assert_eq!(SocketAddress::parse("unix:/run/mcp.sock").unwrap(), path);
+        assert_eq!(SocketAddress::parse("unix:///run/mcp.sock").unwrap(), path);
+        assert_eq!(SocketAddress::parse("/run/mcp.sock").unwrap(), path);
+        assert_eq!(
+            SocketAddress::parse("unix:@mcp-server").unwrap(),
+            SocketAddress::Abstract("mcp-server".to_string())
+        );
+        assert!(SocketAddress::parse("unix:").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_request_over_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+
+        let name = format!("mcp-connect-test-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+        let listener = std::os::unix::net::UnixListener::bind_addr(&addr).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let listener = tokio::net::UnixListener::from_std(listener).unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read_half, mut write_half) = stream.into_split();
+            let mut line = String::new();
+            tokio::io::BufReader::new(read_half).read_line(&mut line).await.unwrap();
+            assert!(line.contains("\"ping\""));
+            write_half.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}\n").await.unwrap();
+        });
+
+        let mut transport = UnixTransport::new(TransportConfig {
+            endpoint: format!("unix:@{}", name),
+            ..Default::default()
+        });
+        transport.connect().await.unwrap();
+
+        let response = transport.send_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).await.unwrap();
+        assert!(response.contains("\"result\""));
+
+        transport.disconnect().await.unwrap();
+        server.await.unwrap();
+    }
+}
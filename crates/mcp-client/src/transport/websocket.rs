@@ -0,0 +1,413 @@
+use crate::error::{ClientError, Result};
+use crate::transport::http::build_headers;
+use crate::transport::pending::PendingRequests;
+use crate::transport::{request_id, Handshake, InboundSender, McpClientTransport, TransportConfig};
+use async_trait::async_trait;
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+/// How often the keepalive task pings the server.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// One JSON-RPC message per text frame, over `ws://` or `wss://`.
+///
+/// If the socket drops, the next message sent reopens it. The new socket is a
+/// new server session, so the client's handshake is repeated on it first.
+pub struct WebSocketTransport {
+    config: TransportConfig,
+    socket: Arc<Mutex<Option<SplitSink<WsStream, Message>>>>,
+    reader_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    keepalive_task: Mutex<Option<JoinHandle<()>>>,
+    pending: Arc<PendingRequests>,
+    /// Last time any frame arrived, used to detect a dead peer
+    last_seen: Arc<Mutex<Instant>>,
+    connected: Arc<Mutex<bool>>,
+    handshake: Mutex<Handshake>,
+    reconnects: AtomicUsize,
+    inbound: Option<InboundSender>,
+}
+
+impl WebSocketTransport {
+    pub fn new(config: TransportConfig) -> Self {
+        Self {
+            config,
+            socket: Arc::new(Mutex::new(None)),
+            reader_task: Arc::new(Mutex::new(None)),
+            keepalive_task: Mutex::new(None),
+            pending: Arc::new(PendingRequests::new()),
+            last_seen: Arc::new(Mutex::new(Instant::now())),
+            connected: Arc::new(Mutex::new(false)),
+            handshake: Mutex::new(Handshake::default()),
+            reconnects: AtomicUsize::new(0),
+            inbound: None,
+        }
+    }
+
+    /// Performs the WebSocket handshake and starts the reader task.
+    async fn open(&self) -> Result<SplitSink<WsStream, Message>> {
+        let mut request = self.config.endpoint.as_str().into_client_request()
+            .map_err(|e| ClientError::Connection(format!("Invalid WebSocket endpoint '{}': {}", self.config.endpoint, e)))?;
+        request.headers_mut().extend(build_headers(&self.config, "application/json")?);
+
+        let connector = if self.config.tls.is_customized() {
+            Some(Connector::Rustls(Arc::new(self.config.tls.client_config()?)))
+        } else {
+            None
+        };
+
+        let (stream, response) = tokio::time::timeout(
+            self.config.timeout,
+            tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector),
+        )
+        .await
+        .map_err(|_| ClientError::Timeout)?
+        .map_err(|e| ClientError::Connection(format!("WebSocket handshake failed: {}", e)))?;
+
+        debug!("WebSocket handshake completed with HTTP {}", response.status());
+
+        let (sink, stream) = stream.split();
+        *self.last_seen.lock().await = Instant::now();
+
+        let reader = tokio::spawn(read_frames(
+            stream,
+            self.pending.clone(),
+            self.inbound.clone(),
+            self.last_seen.clone(),
+        ));
+        if let Some(previous) = self.reader_task.lock().await.replace(reader) {
+            previous.abort();
+        }
+
+        Ok(sink)
+    }
+
+    async fn open_with_retry(&self) -> Result<SplitSink<WsStream, Message>> {
+        for attempt in 1..=self.config.retry_attempts {
+            match self.open().await {
+                Ok(sink) => return Ok(sink),
+                Err(e) => {
+                    warn!("WebSocket connection attempt {} failed: {}", attempt, e);
+                    if attempt < self.config.retry_attempts {
+                        tokio::time::sleep(self.config.retry_delay).await;
+                    }
+                }
+            }
+        }
+
+        Err(ClientError::Connection(format!(
+            "Failed to connect after {} attempts",
+            self.config.retry_attempts
+        )))
+    }
+
+    async fn send_frame(&self, message: &str) -> Result<()> {
+        let mut socket = self.socket.lock().await;
+
+        if !reader_alive(&self.reader_task).await {
+            if socket.take().is_some() {
+                info!("WebSocket connection lost, reconnecting");
+            }
+            let mut sink = self.open_with_retry().await?;
+
+            // A client re-initializing brings its own handshake
+            let method = serde_json::from_str::<Value>(message).ok()
+                .and_then(|parsed| parsed.get("method").and_then(Value::as_str).map(str::to_string));
+            if method.as_deref() != Some("initialize") {
+                if let Err(e) = self.replay_handshake(&mut sink).await {
+                    // Leave the socket closed so the next send tries again
+                    if let Some(reader) = self.reader_task.lock().await.take() {
+                        reader.abort();
+                    }
+                    return Err(ClientError::Connection(format!("Failed to re-initialize after reconnecting: {}", e)));
+                }
+            }
+            *socket = Some(sink);
+        }
+
+        let sink = socket.as_mut()
+            .ok_or_else(|| ClientError::Connection("Not connected".to_string()))?;
+        sink.send(Message::Text(message.to_string())).await
+            .map_err(|e| ClientError::Transport(format!("WebSocket send failed: {}", e)))?;
+
+        self.handshake.lock().await.record(message);
+        Ok(())
+    }
+
+    /// Repeats the client's `initialize` and `initialized` on a reopened socket.
+    async fn replay_handshake(&self, sink: &mut SplitSink<WsStream, Message>) -> Result<()> {
+        let handshake = self.handshake.lock().await.clone();
+        let Some(mut initialize) = handshake.initialize else {
+            return Ok(()); // The client has not initialized yet
+        };
+
+        // A private id, so the reply cannot be mistaken for one the client awaits
+        let reconnect = self.reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+        let id = json!(format!("mcp-connect-reconnect-{}", reconnect));
+        initialize["id"] = id.clone();
+
+        let slot = self.pending.register(&id);
+        if let Err(e) = sink.send(Message::Text(initialize.to_string())).await {
+            self.pending.remove(&id);
+            return Err(ClientError::Transport(format!("WebSocket send failed: {}", e)));
+        }
+        let response = self.pending.wait(&id, slot, self.config.timeout).await?;
+        if serde_json::from_str::<Value>(&response).is_ok_and(|r| r.get("error").is_some()) {
+            return Err(ClientError::Protocol(format!("Server rejected initialize: {}", response)));
+        }
+
+        if let Some(initialized) = handshake.initialized {
+            sink.send(Message::Text(initialized)).await
+                .map_err(|e| ClientError::Transport(format!("WebSocket send failed: {}", e)))?;
+        }
+        debug!("Replayed initialize handshake after reconnecting");
+        Ok(())
+    }
+
+    fn spawn_keepalive(&self) -> JoinHandle<()> {
+        let socket = self.socket.clone();
+        let reader_task = self.reader_task.clone();
+        let pending = self.pending.clone();
+        let last_seen = self.last_seen.clone();
+        let connected = self.connected.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PING_INTERVAL);
+            interval.tick().await;
+
+            while *connected.lock().await {
+                interval.tick().await;
+                if !reader_alive(&reader_task).await {
+                    continue; // Reopened by the next send
+                }
+
+                // Nothing, not even a pong, since the previous ping
+                if last_seen.lock().await.elapsed() > PING_INTERVAL * 2 {
+                    warn!("WebSocket peer stopped responding, dropping connection");
+                    if let Some(reader) = reader_task.lock().await.take() {
+                        reader.abort();
+                    }
+                    pending.fail_all();
+                    continue;
+                }
+
+                if let Some(sink) = socket.lock().await.as_mut() {
+                    if let Err(e) = sink.send(Message::Ping(Vec::new())).await {
+                        warn!("WebSocket ping failed: {}", e);
+                    }
+                }
+            }
+        })
+    }
+}
+
+async fn reader_alive(reader_task: &Mutex<Option<JoinHandle<()>>>) -> bool {
+    reader_task.lock().await.as_ref().is_some_and(|task| !task.is_finished())
+}
+
+/// Routes incoming frames until the socket closes, then fails pending requests.
+async fn read_frames(
+    mut stream: SplitStream<WsStream>,
+    pending: Arc<PendingRequests>,
+    inbound: Option<InboundSender>,
+    last_seen: Arc<Mutex<Instant>>,
+) {
+    while let Some(frame) = stream.next().await {
+        *last_seen.lock().await = Instant::now();
+
+        let message = match frame {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(_) => {
+                    warn!("Ignoring non-UTF-8 binary frame");
+                    continue;
+                }
+            },
+            Ok(Message::Close(frame)) => {
+                debug!("Server closed the WebSocket: {:?}", frame);
+                break;
+            }
+            // tungstenite answers pings itself; control frames only prove liveness
+            Ok(Message::Ping(_)) | Ok(Message::Pong(_)) | Ok(Message::Frame(_)) => continue,
+            Err(e) => {
+                error!("WebSocket read error: {}", e);
+                break;
+            }
+        };
+
+        debug!("Received message: {}", message);
+        pending.route(&message, inbound.as_ref());
+    }
+
+    pending.fail_all();
+}
+
+#[async_trait]
+impl McpClientTransport for WebSocketTransport {
+    fn set_inbound_sender(&mut self, sender: InboundSender) {
+        self.inbound = Some(sender);
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        info!("Connecting to MCP server via WebSocket: {}", self.config.endpoint);
+
+        let sink = self.open_with_retry().await?;
+        *self.socket.lock().await = Some(sink);
+        *self.connected.lock().await = true;
+
+        *self.keepalive_task.lock().await = Some(self.spawn_keepalive());
+        info!("Successfully connected to MCP server");
+        Ok(())
+    }
+
+    async fn send_request(&self, request: &str) -> Result<String> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        let id = request_id(request)
+            .ok_or_else(|| ClientError::Protocol("Request has no id".to_string()))?;
+
+        let slot = self.pending.register(&id);
+        if let Err(e) = self.send_frame(request).await {
+            self.pending.remove(&id);
+            return Err(e);
+        }
+
+        self.pending.wait(&id, slot, self.config.timeout).await
+    }
+
+    async fn send_message(&self, message: &str) -> Result<()> {
+        if !self.is_connected().await {
+            return Err(ClientError::Connection("Not connected".to_string()));
+        }
+
+        self.send_frame(message).await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        *self.connected.lock().await = false;
+
+        if let Some(keepalive) = self.keepalive_task.lock().await.take() {
+            keepalive.abort();
+        }
+        if let Some(mut sink) = self.socket.lock().await.take() {
+            let _ = sink.send(Message::Close(None)).await;
+        }
+        if let Some(reader) = self.reader_task.lock().await.take() {
+            reader.abort();
+        }
+        self.pending.fail_all();
+
+        info!("Disconnected from MCP server");
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> bool {
+        *self.connected.lock().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+    /// Serves one connection like a spec-compliant server, which rejects requests
+    /// before the handshake. The first other request gets a notification, then its
+    /// response, and then the socket is closed. Returns the methods received.
+    #[allow(clippy::result_large_err)] // The handshake callback signature is fixed by tungstenite
+    async fn serve_session(listener: &TcpListener) -> Vec<String> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let check_headers = |request: &Request, response: Response| {
+            assert_eq!(request.headers()["Authorization"], "Bearer secret");
+            Ok(response)
+        };
+        let mut ws = tokio_tungstenite::accept_hdr_async(stream, check_headers).await.unwrap();
+
+        let mut methods = Vec::new();
+        let mut initialized = false;
+        while let Some(Ok(Message::Text(message))) = ws.next().await {
+            let message: Value = serde_json::from_str(&message).unwrap();
+            let method = message["method"].as_str().unwrap().to_string();
+            methods.push(method.clone());
+
+            let reply = match method.as_str() {
+                "notifications/initialized" => {
+                    initialized = true;
+                    continue;
+                }
+                "initialize" => json!({"jsonrpc": "2.0", "id": message["id"], "result": {}}),
+                _ if !initialized => json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": {"code": -32600, "message": "Not initialized"}
+                }),
+                _ => {
+                    ws.send(Message::Text(r#"{"jsonrpc":"2.0","method":"notifications/progress"}"#.to_string())).await.unwrap();
+                    let reply = json!({"jsonrpc": "2.0", "id": message["id"], "result": {}});
+                    ws.send(Message::Text(reply.to_string())).await.unwrap();
+                    ws.close(None).await.unwrap();
+                    break;
+                }
+            };
+            ws.send(Message::Text(reply.to_string())).await.unwrap();
+        }
+        methods
+    }
+
+    #[tokio::test]
+    async fn test_routes_messages_and_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = TransportConfig {
+            endpoint: format!("ws://{}", listener.local_addr().unwrap()),
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        }
+        .with_auth_token("Bearer secret".to_string());
+
+        let server = tokio::spawn(async move { (serve_session(&listener).await, serve_session(&listener).await) });
+
+        let (inbound_tx, mut inbound_rx) = mpsc::unbounded_channel();
+        let mut transport = WebSocketTransport::new(config);
+        transport.set_inbound_sender(inbound_tx);
+        transport.connect().await.unwrap();
+
+        transport.send_request(r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#).await.unwrap();
+        transport.send_message(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).await.unwrap();
+        let response = transport.send_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).await.unwrap();
+        assert!(response.contains(r#""id":1"#));
+        assert!(inbound_rx.recv().await.unwrap().contains("notifications/progress"));
+
+        // The server closed the socket after replying; the next request reopens it
+        // and the handshake is repeated before it
+        while reader_alive(&transport.reader_task).await {
+            tokio::task::yield_now().await;
+        }
+        let response: Value = serde_json::from_str(
+            &transport.send_request(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#).await.unwrap(),
+        )
+        .unwrap();
+        assert_eq!(response, json!({"jsonrpc": "2.0", "id": 2, "result": {}}));
+
+        transport.disconnect().await.unwrap();
+        let (first, second) = server.await.unwrap();
+        assert_eq!(first, ["initialize", "notifications/initialized", "ping"]);
+        assert_eq!(second, ["initialize", "notifications/initialized", "ping"]);
+    }
+}
@@ -3,7 +3,7 @@
 //! Command-line interface for the Model Context Protocol (MCP) remote proxy system.
 //!
 //! This application bridges local MCP clients with remote MCP servers, providing:
-//! - Multiple transport support (HTTP, STDIO, TCP)
+//! - Multiple transport support (HTTP, legacy SSE, WebSocket, STDIO, TCP, Unix sockets)
 //! - Authentication handling (Bearer tokens, API keys, OAuth 2.1)
 //! - Fallback mechanisms and load balancing
 //! - Comprehensive logging and debugging
@@ -33,13 +33,14 @@
 //! - `notification-demo`: Test MCP notification system
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use mcp_client::{McpRemoteClient, transport::TransportConfig};
+use clap::{Args, Parser, Subcommand};
+use mcp_client::{ClientError, McpRemoteClient, VersionPolicy, pagination::DEFAULT_MAX_PAGES, protocol::{parse_protocol_version, ProtocolVersion}, transport::{Framing, ProxyConfig, RestartPolicy, StderrHandling, StdioConfig, TlsConfig, TlsVersion, TransportConfig}};
 use mcp_proxy::{stdio_proxy::StdioProxyBuilder, strategy::{ForwardingStrategy, LoadBalancingStrategy}};
 use mcp_types::{TransportType, McpClient, LogLevel};
-use serde_json::json;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{error, info, warn, Level};
@@ -77,6 +78,9 @@ enum Commands {
         #[arg(long, help = "Fallback transport types", value_delimiter = ',')]
         fallbacks: Option<Vec<String>>,
 
+        #[arg(long, help = "Command line for the STDIO fallback server", default_value = "mcp-server")]
+        stdio_command: String,
+
         #[arg(long, help = "Connection timeout in seconds", default_value = "30")]
         timeout: u64,
 
@@ -97,6 +101,30 @@ enum Commands {
 
         #[arg(long, help = "Custom User-Agent header")]
         user_agent: Option<String>,
+
+        #[arg(long, help = "Maximum number of requests forwarded concurrently", default_value = "32")]
+        max_in_flight: usize,
+
+        #[arg(long, help = "Framing for STDIO, TCP and Unix socket servers: ndjson, content-length or length-prefixed", default_value = "ndjson", value_parser = parse_framing)]
+        framing: Framing,
+
+        #[arg(long, help = "Framing of messages on our own stdin and stdout: ndjson, content-length or length-prefixed", default_value = "ndjson", value_parser = parse_framing)]
+        client_framing: Framing,
+
+        #[arg(long, help = "JSON merge patch applied to the capabilities the local client declares in initialize", value_parser = parse_json)]
+        capabilities: Option<Value>,
+
+        #[command(flatten)]
+        tls: TlsArgs,
+
+        #[command(flatten)]
+        proxy: ProxyArgs,
+
+        #[command(flatten)]
+        stdio: StdioArgs,
+
+        #[command(flatten)]
+        protocol: ProtocolArgs,
     },
 
     /// Run with load balancing across multiple endpoints
@@ -127,6 +155,30 @@ enum Commands {
 
         #[arg(long, help = "Custom User-Agent header")]
         user_agent: Option<String>,
+
+        #[arg(long, help = "Maximum number of requests forwarded concurrently", default_value = "32")]
+        max_in_flight: usize,
+
+        #[arg(long, help = "Framing for STDIO, TCP and Unix socket servers: ndjson, content-length or length-prefixed", default_value = "ndjson", value_parser = parse_framing)]
+        framing: Framing,
+
+        #[arg(long, help = "Framing of messages on our own stdin and stdout: ndjson, content-length or length-prefixed", default_value = "ndjson", value_parser = parse_framing)]
+        client_framing: Framing,
+
+        #[arg(long, help = "JSON merge patch applied to the capabilities the local client declares in initialize", value_parser = parse_json)]
+        capabilities: Option<Value>,
+
+        #[command(flatten)]
+        tls: TlsArgs,
+
+        #[command(flatten)]
+        proxy: ProxyArgs,
+
+        #[command(flatten)]
+        stdio: StdioArgs,
+
+        #[command(flatten)]
+        protocol: ProtocolArgs,
     },
 
     /// Test connection to a remote MCP server
@@ -151,6 +203,21 @@ enum Commands {
 
         #[arg(long, help = "Custom User-Agent header")]
         user_agent: Option<String>,
+
+        #[arg(long, help = "Framing for STDIO, TCP and Unix socket servers: ndjson, content-length or length-prefixed", default_value = "ndjson", value_parser = parse_framing)]
+        framing: Framing,
+
+        #[command(flatten)]
+        tls: TlsArgs,
+
+        #[command(flatten)]
+        proxy: ProxyArgs,
+
+        #[command(flatten)]
+        stdio: StdioArgs,
+
+        #[command(flatten)]
+        protocol: ProtocolArgs,
     },
 
     /// Demo MCP server notifications
@@ -160,11 +227,174 @@ enum Commands {
     },
 }
 
+/// TLS options for HTTPS, WSS and `tls://` TCP endpoints.
+#[derive(Args, Clone)]
+struct TlsArgs {
+    #[arg(long, help = "PEM bundle of additional CA certificates to trust")]
+    tls_ca_cert: Option<PathBuf>,
+
+    #[arg(long, help = "PEM client certificate for mutual TLS", requires = "tls_client_key")]
+    tls_client_cert: Option<PathBuf>,
+
+    #[arg(long, help = "PEM private key for the client certificate", requires = "tls_client_cert")]
+    tls_client_key: Option<PathBuf>,
+
+    #[arg(long, help = "PKCS#12 client identity for mutual TLS", conflicts_with = "tls_client_cert")]
+    tls_client_pkcs12: Option<PathBuf>,
+
+    #[arg(long, help = "Password for the PKCS#12 client identity", requires = "tls_client_pkcs12")]
+    tls_client_pkcs12_password: Option<String>,
+
+    #[arg(long, help = "Minimum TLS version (1.2 or 1.3)", value_parser = parse_tls_version)]
+    tls_min_version: Option<TlsVersion>,
+
+    #[arg(long, help = "Server name for SNI and certificate verification")]
+    tls_server_name: Option<String>,
+
+    #[arg(long, help = "Pinned server public key hash (sha256//<base64>)", value_delimiter = ',')]
+    tls_pin: Vec<String>,
+}
+
+impl From<TlsArgs> for TlsConfig {
+    fn from(args: TlsArgs) -> Self {
+        TlsConfig {
+            ca_cert: args.tls_ca_cert,
+            client_cert: args.tls_client_cert,
+            client_key: args.tls_client_key,
+            client_pkcs12: args.tls_client_pkcs12,
+            client_pkcs12_password: args.tls_client_pkcs12_password,
+            min_version: args.tls_min_version,
+            server_name: args.tls_server_name,
+            pinned_keys: args.tls_pin,
+        }
+    }
+}
+
+/// Egress proxy options for HTTP, SSE and TCP endpoints.
+#[derive(Args, Clone)]
+struct ProxyArgs {
+    #[arg(long, help = "Proxy URL (http://, socks5:// or socks5h://); defaults to HTTPS_PROXY/ALL_PROXY")]
+    proxy: Option<String>,
+
+    #[arg(long, help = "Proxy username")]
+    proxy_user: Option<String>,
+
+    #[arg(long, help = "Proxy password", requires = "proxy_user")]
+    proxy_password: Option<String>,
+
+    #[arg(long, help = "Hosts to reach without the proxy, in addition to NO_PROXY", value_delimiter = ',')]
+    no_proxy: Vec<String>,
+}
+
+impl From<ProxyArgs> for ProxyConfig {
+    fn from(args: ProxyArgs) -> Self {
+        ProxyConfig {
+            url: args.proxy,
+            username: args.proxy_user,
+            password: args.proxy_password,
+            no_proxy: args.no_proxy,
+        }
+    }
+}
+
+/// Protocol version negotiation options.
+#[derive(Args, Clone)]
+struct ProtocolArgs {
+    #[arg(long, help = "Protocol version to offer in initialize (default: 2025-03-26)", value_parser = parse_protocol_version_arg)]
+    protocol_version: Option<ProtocolVersion>,
+
+    #[arg(long, help = "Protocol versions to accept from the server (default: all known)", value_delimiter = ',', value_parser = parse_protocol_version_arg)]
+    supported_protocol_versions: Option<Vec<ProtocolVersion>>,
+}
+
+impl From<ProtocolArgs> for VersionPolicy {
+    fn from(args: ProtocolArgs) -> Self {
+        let mut policy = args.protocol_version.map(VersionPolicy::new).unwrap_or_default();
+        if let Some(supported) = args.supported_protocol_versions {
+            policy = policy.with_supported(supported);
+        }
+        policy
+    }
+}
+
+/// Options for launching STDIO servers, whose command line is the endpoint.
+#[derive(Args, Clone)]
+struct StdioArgs {
+    #[arg(long, help = "Extra argument for the STDIO server (repeatable)", allow_hyphen_values = true)]
+    stdio_arg: Vec<String>,
+
+    #[arg(long, help = "Environment variable for the STDIO server in KEY=VALUE format (repeatable)", value_parser = parse_env_var)]
+    stdio_env: Vec<(String, String)>,
+
+    #[arg(long, help = "Working directory for the STDIO server")]
+    stdio_cwd: Option<PathBuf>,
+
+    #[arg(long, help = "Start the STDIO server with an empty environment")]
+    stdio_clear_env: bool,
+
+    #[arg(long, help = "Where STDIO server stderr goes: both, notify, log or discard", default_value = "both", value_parser = parse_stderr_handling)]
+    stdio_stderr: StderrHandling,
+
+    #[arg(long, help = "Maximum STDIO server stderr lines forwarded per second (0 for no limit)", default_value = "20")]
+    stdio_stderr_rate: u32,
+
+    #[arg(long, help = "Restarts allowed per minute when the STDIO server crashes (0 to disable)", default_value = "5")]
+    stdio_max_restarts: u32,
+}
+
+impl From<StdioArgs> for StdioConfig {
+    fn from(args: StdioArgs) -> Self {
+        StdioConfig {
+            args: args.stdio_arg,
+            env: args.stdio_env.into_iter().collect(),
+            cwd: args.stdio_cwd,
+            clear_env: args.stdio_clear_env,
+            stderr: args.stdio_stderr,
+            stderr_lines_per_second: args.stdio_stderr_rate,
+            restart: RestartPolicy {
+                enabled: args.stdio_max_restarts > 0,
+                max_restarts: args.stdio_max_restarts,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+fn parse_env_var(var: &str) -> Result<(String, String)> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(anyhow::anyhow!("Invalid environment variable '{}': expected KEY=VALUE", var)),
+    }
+}
+
+fn parse_stderr_handling(handling: &str) -> Result<StderrHandling> {
+    Ok(handling.parse()?)
+}
+
+fn parse_protocol_version_arg(version: &str) -> Result<ProtocolVersion> {
+    Ok(parse_protocol_version(version)?)
+}
+
+fn parse_framing(framing: &str) -> Result<Framing> {
+    Ok(framing.parse()?)
+}
+
+fn parse_json(json: &str) -> Result<Value> {
+    serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Invalid JSON '{}': {}", json, e))
+}
+
+fn parse_tls_version(version: &str) -> Result<TlsVersion> {
+    Ok(version.parse()?)
+}
+
 fn parse_transport_type(transport: &str) -> Result<TransportType> {
     match transport.to_lowercase().as_str() {
         "http" => Ok(TransportType::Http),
         "stdio" => Ok(TransportType::Stdio),
         "tcp" => Ok(TransportType::Tcp),
+        "sse" => Ok(TransportType::Sse),
+        "websocket" | "ws" => Ok(TransportType::WebSocket),
+        "unix" => Ok(TransportType::Unix),
         _ => Err(anyhow::anyhow!("Unknown transport type: {}", transport)),
     }
 }
@@ -191,6 +421,7 @@ fn parse_headers(headers: Option<Vec<String>>) -> Result<HashMap<String, String>
     Ok(header_map)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_transport_config(
     endpoint: String,
     timeout: u64,
@@ -200,6 +431,10 @@ fn build_transport_config(
     auth_token: Option<String>,
     api_key: Option<String>,
     user_agent: Option<String>,
+    tls: TlsConfig,
+    proxy: ProxyConfig,
+    stdio: StdioConfig,
+    framing: Framing,
 ) -> Result<TransportConfig> {
     let mut config = TransportConfig {
         endpoint,
@@ -209,6 +444,10 @@ fn build_transport_config(
         headers: parse_headers(headers)?,
         auth_token: None,
         user_agent,
+        tls,
+        proxy,
+        stdio,
+        framing,
     };
 
     // Handle authentication
@@ -222,7 +461,7 @@ fn build_transport_config(
 }
 
 // Simple function to send MCP notifications to STDOUT
-fn send_mcp_notification(level: LogLevel, message: &str) {
+fn send_mcp_notification(framing: Framing, level: LogLevel, message: &str) {
     let notification = json!({
         "jsonrpc": "2.0",
         "method": "notifications/message",
@@ -234,8 +473,9 @@ fn send_mcp_notification(level: LogLevel, message: &str) {
     });
 
     if let Ok(json_str) = serde_json::to_string(&notification) {
-        println!("{}", json_str);
-        let _ = io::stdout().flush();
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(&framing.encode(&json_str));
+        let _ = stdout.flush();
     }
 }
 
@@ -315,19 +555,19 @@ async fn run_notification_demo(count: u32) -> Result<()> {
     for i in 1..=count {
         match i % 4 {
             1 => {
-                send_mcp_notification(LogLevel::Info, &format!("Demo info message {}", i));
+                send_mcp_notification(Framing::Ndjson, LogLevel::Info, &format!("Demo info message {}", i));
                 info!("Sent info notification {}", i);
             }
             2 => {
-                send_mcp_notification(LogLevel::Warn, &format!("Demo warning message {}", i));
+                send_mcp_notification(Framing::Ndjson, LogLevel::Warn, &format!("Demo warning message {}", i));
                 warn!("Sent warning notification {}", i);
             }
             3 => {
-                send_mcp_notification(LogLevel::Error, &format!("Demo error message {}", i));
+                send_mcp_notification(Framing::Ndjson, LogLevel::Error, &format!("Demo error message {}", i));
                 error!("Sent error notification {}", i);
             }
             0 => {
-                send_mcp_notification(LogLevel::Debug, &format!("Demo debug message {}", i));
+                send_mcp_notification(Framing::Ndjson, LogLevel::Debug, &format!("Demo debug message {}", i));
                 info!("Sent debug notification {}", i);
             }
             _ => unreachable!(),
@@ -341,9 +581,11 @@ async fn run_notification_demo(count: u32) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_proxy(
     endpoint: String,
     fallbacks: Option<Vec<String>>,
+    stdio_command: String,
     timeout: u64,
     retry_attempts: u32,
     retry_delay: u64,
@@ -351,13 +593,21 @@ async fn run_proxy(
     auth_token: Option<String>,
     api_key: Option<String>,
     user_agent: Option<String>,
+    max_in_flight: usize,
+    tls: TlsConfig,
+    proxy: ProxyConfig,
+    stdio: StdioConfig,
+    framing: Framing,
+    client_framing: Framing,
+    capabilities: Option<Value>,
+    versions: VersionPolicy,
     debug: bool,
 ) -> Result<()> {
     info!("Starting MCP Remote Proxy");
     info!("Primary endpoint: {}", endpoint);
 
     // Send MCP notification that proxy is starting
-    send_mcp_notification(LogLevel::Info, &format!("MCP Proxy starting with endpoint: {}", endpoint));
+    send_mcp_notification(client_framing, LogLevel::Info, &format!("MCP Proxy starting with endpoint: {}", endpoint));
 
     let fallback_transports = if let Some(fallbacks) = fallbacks {
         parse_fallback_transports(&fallbacks)?
@@ -377,25 +627,45 @@ async fn run_proxy(
         auth_token,
         api_key,
         user_agent,
+        tls,
+        proxy,
+        StdioConfig::default(),
+        framing,
     )?;
 
-    let client = McpRemoteClient::new_with_config(primary_config, fallback_transports);
-    let strategy = Arc::new(ForwardingStrategy::new(client));
+    let stdio_config = TransportConfig {
+        endpoint: stdio_command,
+        ..Default::default()
+    }
+    .with_stdio(stdio)
+    .with_framing(framing);
+
+    let client = McpRemoteClient::new_with_config(primary_config, fallback_transports)
+        .with_fallback_config(TransportType::Stdio, stdio_config)
+        .with_version_policy(versions);
+    let mut strategy = ForwardingStrategy::new(client);
+    if let Some(capabilities) = capabilities {
+        strategy = strategy.with_capability_overrides(capabilities);
+    }
+    let strategy = Arc::new(strategy);
 
     let proxy = StdioProxyBuilder::new()
         .with_strategy(strategy)
         .with_debug_mode(debug)
+        .with_max_in_flight(max_in_flight)
+        .with_framing(client_framing)
         .build()?;
 
     info!("Proxy ready, listening on STDIO");
 
     // Send MCP notification that proxy is ready
-    send_mcp_notification(LogLevel::Info, "MCP Proxy ready and listening for requests");
+    send_mcp_notification(client_framing, LogLevel::Info, "MCP Proxy ready and listening for requests");
     proxy.run().await?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_load_balance(
     endpoints: Vec<String>,
     transport: String,
@@ -406,6 +676,14 @@ async fn run_load_balance(
     auth_token: Option<String>,
     api_key: Option<String>,
     user_agent: Option<String>,
+    max_in_flight: usize,
+    tls: TlsConfig,
+    proxy: ProxyConfig,
+    stdio: StdioConfig,
+    framing: Framing,
+    client_framing: Framing,
+    capabilities: Option<Value>,
+    versions: VersionPolicy,
     debug: bool,
 ) -> Result<()> {
     info!("Starting MCP Load Balancing Proxy");
@@ -425,10 +703,15 @@ async fn run_load_balance(
             auth_token.clone(),
             api_key.clone(),
             user_agent.clone(),
+            tls.clone(),
+            proxy.clone(),
+            stdio.clone(),
+            framing,
         )?;
 
         let transports = vec![(transport_type.clone(), config)];
-        let client = McpRemoteClient::with_custom_transports(transports).await;
+        let client = McpRemoteClient::with_custom_transports(transports).await
+            .with_version_policy(versions.clone());
         clients.push(client);
         info!("Added client for endpoint: {}", endpoint);
     }
@@ -437,11 +720,17 @@ async fn run_load_balance(
         return Err(anyhow::anyhow!("No clients configured"));
     }
 
-    let strategy = Arc::new(LoadBalancingStrategy::new(clients));
+    let mut strategy = LoadBalancingStrategy::new(clients);
+    if let Some(capabilities) = capabilities {
+        strategy = strategy.with_capability_overrides(capabilities);
+    }
+    let strategy = Arc::new(strategy);
 
     let proxy = StdioProxyBuilder::new()
         .with_strategy(strategy)
         .with_debug_mode(debug)
+        .with_max_in_flight(max_in_flight)
+        .with_framing(client_framing)
         .build()?;
 
     info!("Load balancing proxy ready, listening on STDIO");
@@ -450,6 +739,7 @@ async fn run_load_balance(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn test_connection(
     endpoint: String,
     transport: String,
@@ -458,6 +748,11 @@ async fn test_connection(
     auth_token: Option<String>,
     api_key: Option<String>,
     user_agent: Option<String>,
+    tls: TlsConfig,
+    proxy: ProxyConfig,
+    stdio: StdioConfig,
+    framing: Framing,
+    versions: VersionPolicy,
 ) -> Result<()> {
     info!("Testing connection to: {}", endpoint);
     info!("Transport: {}", transport);
@@ -472,10 +767,15 @@ async fn test_connection(
         auth_token,
         api_key,
         user_agent,
+        tls,
+        proxy,
+        stdio,
+        framing,
     )?;
 
     let transports = vec![(transport_type, config)];
-    let client = McpRemoteClient::with_custom_transports(transports).await;
+    let client = McpRemoteClient::with_custom_transports(transports).await
+        .with_version_policy(versions);
 
     // Test connection
     info!("Connecting...");
@@ -486,16 +786,25 @@ async fn test_connection(
     let init_result = client.initialize().await?;
     info!("Server info: {} v{}", init_result.server_info.name, init_result.server_info.version);
     info!("Protocol version: {:?}", init_result.protocol_version);
+    info!("Server capabilities: {}", serde_json::to_string(&init_result.capabilities)?);
 
     info!("Testing tools list...");
-    match client.list_tools().await {
-        Ok(tools) => info!("Tools: {}", tools),
+    match client.list_all_tools(DEFAULT_MAX_PAGES).await {
+        Ok(tools) => {
+            let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_ref()).collect();
+            info!("Tools ({}): {}", tools.len(), names.join(", "));
+        }
+        Err(e @ ClientError::CapabilityNotSupported { .. }) => info!("Skipping: {}", e),
         Err(e) => warn!("Failed to list tools: {}", e),
     }
 
     info!("Testing resources list...");
-    match client.list_resources().await {
-        Ok(resources) => info!("Resources: {}", resources),
+    match client.list_all_resources(DEFAULT_MAX_PAGES).await {
+        Ok(resources) => {
+            let uris: Vec<&str> = resources.iter().map(|resource| resource.uri.as_str()).collect();
+            info!("Resources ({}): {}", resources.len(), uris.join(", "));
+        }
+        Err(e @ ClientError::CapabilityNotSupported { .. }) => info!("Skipping: {}", e),
         Err(e) => warn!("Failed to list resources: {}", e),
     }
 
@@ -516,6 +825,7 @@ async fn main() -> Result<()> {
         Commands::Proxy {
             endpoint,
             fallbacks,
+            stdio_command,
             timeout,
             retry_attempts,
             retry_delay,
@@ -523,10 +833,19 @@ async fn main() -> Result<()> {
             auth_token,
             api_key,
             user_agent,
+            max_in_flight,
+            framing,
+            client_framing,
+            capabilities,
+            tls,
+            proxy,
+            stdio,
+            protocol,
         } => {
             run_proxy(
                 endpoint,
                 fallbacks,
+                stdio_command,
                 timeout,
                 retry_attempts,
                 retry_delay,
@@ -534,6 +853,14 @@ async fn main() -> Result<()> {
                 auth_token,
                 api_key,
                 user_agent,
+                max_in_flight,
+                tls.into(),
+                proxy.into(),
+                stdio.into(),
+                framing,
+                client_framing,
+                capabilities,
+                protocol.into(),
                 cli.debug
             ).await
         }
@@ -548,6 +875,14 @@ async fn main() -> Result<()> {
             auth_token,
             api_key,
             user_agent,
+            max_in_flight,
+            framing,
+            client_framing,
+            capabilities,
+            tls,
+            proxy,
+            stdio,
+            protocol,
         } => {
             run_load_balance(
                 endpoints,
@@ -559,6 +894,14 @@ async fn main() -> Result<()> {
                 auth_token,
                 api_key,
                 user_agent,
+                max_in_flight,
+                tls.into(),
+                proxy.into(),
+                stdio.into(),
+                framing,
+                client_framing,
+                capabilities,
+                protocol.into(),
                 cli.debug
             ).await
         }
@@ -571,6 +914,11 @@ async fn main() -> Result<()> {
             auth_token,
             api_key,
             user_agent,
+            framing,
+            tls,
+            proxy,
+            stdio,
+            protocol,
         } => {
             test_connection(
                 endpoint,
@@ -580,6 +928,11 @@ async fn main() -> Result<()> {
                 auth_token,
                 api_key,
                 user_agent,
+                tls.into(),
+                proxy.into(),
+                stdio.into(),
+                framing,
+                protocol.into(),
             ).await
         }
 
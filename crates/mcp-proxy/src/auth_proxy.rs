@@ -1,5 +1,5 @@
 use crate::error::{ProxyError, Result};
-use mcp_client::{OAuthClient, OAuthClientConfig, ClientToken};
+use mcp_client::{OAuthClient, OAuthClientConfig, ClientToken, ProxyConfig};
 use mcp_server::{OAuthManager, OAuthConfig};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -13,6 +13,8 @@ pub struct AuthProxyConfig {
     pub client_oauth: Option<OAuthClientConfig>,
     pub require_auth: bool,
     pub token_validation_endpoint: Option<String>,
+    /// Egress proxy for calls to the token endpoints
+    pub proxy: ProxyConfig,
 }
 
 pub struct AuthenticatedProxy {
@@ -264,7 +266,12 @@ impl AuthenticatedProxy {
     }
 
     async fn validate_token(&self, token: &str, endpoint: &str) -> Result<()> {
-        let client = reqwest::Client::new();
+        let proxy = self.config.proxy.reqwest_proxy()
+            .map_err(|e| ProxyError::Auth(format!("Invalid proxy configuration: {}", e)))?;
+        let client = reqwest::Client::builder()
+            .proxy(proxy)
+            .build()
+            .map_err(|e| ProxyError::Auth(format!("Failed to build HTTP client: {}", e)))?;
         let response = client
             .get(endpoint)
             .bearer_auth(token)
@@ -311,6 +318,7 @@ mod tests {
             client_oauth: None,
             require_auth: false,
             token_validation_endpoint: None,
+            proxy: ProxyConfig::default(),
         }
     }
 
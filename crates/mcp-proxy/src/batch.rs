@@ -0,0 +1,197 @@
+//! JSON-RPC batches: splitting an incoming array into its messages,
+//! dispatching them and assembling the replies into a single array.
+//!
+//! Both proxies share [`dispatch`] and differ only in how they run a request,
+//! which they supply through [`BatchHandler`].
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// One message of a batch, sorted by how the proxy has to handle it.
+#[derive(Debug, PartialEq)]
+pub(crate) enum BatchItem {
+    /// Expects a reply, which goes into the batch response
+    Request { id: Value, message: String },
+    Notification(String),
+    /// The local client's reply to a server-initiated request
+    Response(String),
+    /// Not a valid JSON-RPC message; carries the error reply for it
+    Invalid(String),
+}
+
+/// Splits `message` into its items if it is a batch, or returns `None` for a single message.
+pub(crate) fn parse(message: &str) -> Option<Vec<BatchItem>> {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Array(entries)) => Some(split(entries)),
+        _ => None,
+    }
+}
+
+pub(crate) fn split(entries: Vec<Value>) -> Vec<BatchItem> {
+    entries.into_iter().map(classify).collect()
+}
+
+fn classify(entry: Value) -> BatchItem {
+    let Some(object) = entry.as_object() else {
+        return BatchItem::Invalid(invalid_request(&Value::Null));
+    };
+
+    let id = object.get("id").cloned();
+    match (object.get("method").and_then(Value::as_str), id) {
+        (Some(_), Some(id)) => BatchItem::Request { id, message: entry.to_string() },
+        (Some(_), None) => BatchItem::Notification(entry.to_string()),
+        (None, Some(_)) if object.contains_key("result") || object.contains_key("error") => {
+            BatchItem::Response(entry.to_string())
+        }
+        (None, id) => BatchItem::Invalid(invalid_request(&id.unwrap_or(Value::Null))),
+    }
+}
+
+/// Runs the messages of a batch for [`dispatch`].
+#[async_trait]
+pub(crate) trait BatchHandler: Sync {
+    /// Handles a notification or a response.
+    async fn handle_message(&self, message: &str);
+
+    /// Starts forwarding a request. Its reply, if any, goes to `replies`, and a
+    /// clone of the sender must be held until then.
+    async fn start_request(&self, id: Value, message: String, replies: &mpsc::UnboundedSender<String>);
+}
+
+/// The replies to a batch, which arrive as its requests complete.
+pub(crate) struct Replies {
+    empty: bool,
+    rx: mpsc::UnboundedReceiver<String>,
+}
+
+impl Replies {
+    /// Waits for the last reply and joins them into the batch response.
+    pub(crate) async fn collect(mut self) -> Option<String> {
+        if self.empty {
+            return Some(invalid_request(&Value::Null));
+        }
+
+        let mut replies = Vec::new();
+        while let Some(reply) = self.rx.recv().await {
+            replies.push(reply);
+        }
+        assemble(replies)
+    }
+}
+
+/// Handles each message of a batch in order: notifications and responses
+/// directly, requests by starting them, and invalid entries by their error reply.
+pub(crate) async fn dispatch(items: Vec<BatchItem>, handler: &impl BatchHandler) -> Replies {
+    // Every started request holds a sender, so the channel closes after the last reply
+    let (reply_tx, rx) = mpsc::unbounded_channel();
+    let empty = items.is_empty();
+
+    for item in items {
+        match item {
+            BatchItem::Request { id, message } => handler.start_request(id, message, &reply_tx).await,
+            BatchItem::Notification(message) | BatchItem::Response(message) => handler.handle_message(&message).await,
+            BatchItem::Invalid(reply) => {
+                let _ = reply_tx.send(reply);
+            }
+        }
+    }
+
+    Replies { empty, rx }
+}
+
+/// The JSON-RPC `Invalid Request` error, which is also the reply to an empty batch.
+pub(crate) fn invalid_request(id: &Value) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": -32600,
+            "message": "Invalid Request"
+        }
+    })
+    .to_string()
+}
+
+/// Joins the replies to a batch into one array; a batch of notifications gets no reply at all.
+fn assemble(replies: Vec<String>) -> Option<String> {
+    if replies.is_empty() {
+        return None;
+    }
+    Some(format!("[{}]", replies.join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_batch() {
+        let items = parse(r#"[
+            {"jsonrpc":"2.0","id":1,"method":"tools/list"},
+            {"jsonrpc":"2.0","method":"notifications/initialized"},
+            {"jsonrpc":"2.0","id":"s1","result":{}},
+            {"jsonrpc":"2.0","id":2},
+            3
+        ]"#)
+        .unwrap();
+
+        assert!(matches!(&items[0], BatchItem::Request { id, .. } if *id == json!(1)));
+        assert!(matches!(&items[1], BatchItem::Notification(_)));
+        assert!(matches!(&items[2], BatchItem::Response(_)));
+        assert!(matches!(&items[3], BatchItem::Invalid(reply) if reply.contains(r#""id":2"#)));
+        assert!(matches!(&items[4], BatchItem::Invalid(reply) if reply.contains(r#""id":null"#)));
+        assert_eq!(parse(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#), None);
+    }
+
+    /// Answers requests at once and records the order messages were handled in.
+    #[derive(Default)]
+    struct Recorder {
+        handled: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl BatchHandler for Recorder {
+        async fn handle_message(&self, message: &str) {
+            self.handled.lock().unwrap().push(message.to_string());
+        }
+
+        async fn start_request(&self, id: Value, message: String, replies: &mpsc::UnboundedSender<String>) {
+            self.handled.lock().unwrap().push(message);
+            let _ = replies.send(json!({"jsonrpc": "2.0", "id": id, "result": {}}).to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_batch() {
+        let recorder = Recorder::default();
+        let items = parse(r#"[
+            {"jsonrpc":"2.0","method":"notifications/initialized"},
+            {"jsonrpc":"2.0","id":1,"method":"ping"},
+            {"jsonrpc":"2.0","id":2}
+        ]"#)
+        .unwrap();
+
+        let reply = dispatch(items, &recorder).await.collect().await.unwrap();
+        let reply: Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply[0], json!({"jsonrpc": "2.0", "id": 1, "result": {}}));
+        assert_eq!(reply[1]["error"]["code"], -32600);
+        assert_eq!(recorder.handled.lock().unwrap().len(), 2);
+        assert!(recorder.handled.lock().unwrap()[0].contains("notifications/initialized"));
+
+        let empty = dispatch(Vec::new(), &recorder).await.collect().await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&empty).unwrap()["error"]["code"], -32600);
+
+        let notifications = parse(r#"[{"jsonrpc":"2.0","method":"notifications/initialized"}]"#).unwrap();
+        assert_eq!(dispatch(notifications, &recorder).await.collect().await, None);
+    }
+
+    #[test]
+    fn test_assemble_replies() {
+        assert_eq!(assemble(Vec::new()), None);
+
+        let reply = assemble(vec![r#"{"id":1}"#.to_string(), r#"{"id":2}"#.to_string()]).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&reply).unwrap(), json!([{"id": 1}, {"id": 2}]));
+    }
+}
@@ -0,0 +1,146 @@
+//! The local client's `initialize` handshake, relayed to the remote server(s).
+//!
+//! The remote server should see the real client: its name, version and
+//! capabilities. The strategies therefore forward the client's own params,
+//! with any configured capability overrides merged in, and initialize each
+//! remote connection only once, answering later `initialize` requests from the
+//! cached result.
+//!
+//! When the local client asks for a protocol version the proxy supports but
+//! the server settles on another, the client is still told its own version and
+//! [`Shims`] bridge the difference.
+
+use crate::error::Result;
+use mcp_client::protocol::{protocol_version_of, Shims, VersionPolicy};
+use serde_json::{Map, Value};
+
+/// Whether `message` is an `initialize` request.
+pub(crate) fn is_initialize(message: &str) -> bool {
+    serde_json::from_str::<Value>(message)
+        .ok()
+        .and_then(|v| v.get("method").and_then(Value::as_str).map(|m| m == "initialize"))
+        .unwrap_or(false)
+}
+
+/// Whether `message` is the `notifications/initialized` that completes the handshake.
+pub(crate) fn is_initialized_notification(message: &str) -> bool {
+    serde_json::from_str::<Value>(message)
+        .ok()
+        .and_then(|v| v.get("method").and_then(Value::as_str).map(|m| m == "notifications/initialized"))
+        .unwrap_or(false)
+}
+
+/// Extracts the id and params of an `initialize` request, applying `overrides`
+/// to the client's capabilities as a JSON merge patch (RFC 7396).
+pub(crate) fn initialize_params(request: &str, overrides: Option<&Value>) -> Result<(Value, Value)> {
+    let mut parsed: Value = serde_json::from_str(request)?;
+    let id = parsed.get("id").cloned().unwrap_or(Value::Null);
+    let mut params = parsed.get_mut("params").map(Value::take).unwrap_or_else(|| Value::Object(Map::new()));
+
+    if let (Some(overrides), Some(params)) = (overrides, params.as_object_mut()) {
+        let capabilities = params.entry("capabilities").or_insert_with(|| Value::Object(Map::new()));
+        merge_patch(capabilities, overrides);
+    }
+
+    Ok((id, params))
+}
+
+/// Applies `patch` to `target`: objects merge recursively, `null` removes a key
+/// and anything else replaces the existing value.
+pub(crate) fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!("target was just made an object");
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Settles the protocol version the local client is told, rewriting `result`,
+/// and returns the shims needed when the client is older than the server.
+///
+/// A server on an older version than the client's is passed through unchanged:
+/// the client must not believe it has features the server never offers.
+pub(crate) fn bridge(params: &Value, policy: &VersionPolicy, result: &mut Value) -> Option<Shims> {
+    let requested = protocol_version_of(params).filter(|version| policy.accepts(version))?;
+    let negotiated = protocol_version_of(result)?;
+    if requested >= negotiated {
+        return None;
+    }
+
+    result["protocolVersion"] = Value::String(requested.to_string());
+    Some(Shims::new(requested, negotiated))
+}
+
+/// The reply to the local client's `initialize`, carrying the remote server's result.
+pub(crate) fn reply(id: &Value, result: &Value) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": result
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_patch() {
+        let mut target = json!({"roots": {"listChanged": true}, "sampling": {}});
+        merge_patch(&mut target, &json!({"roots": {"listChanged": false}, "sampling": null, "elicitation": {}}));
+        assert_eq!(target, json!({"roots": {"listChanged": false}, "elicitation": {}}));
+    }
+
+    #[test]
+    fn test_initialize_params_keep_client_identity() {
+        let request = r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{"sampling":{}},"clientInfo":{"name":"editor","version":"2.1"}}}"#;
+
+        let (id, params) = initialize_params(request, Some(&json!({"sampling": null, "roots": {}}))).unwrap();
+        assert_eq!(id, json!(0));
+        assert_eq!(params["clientInfo"], json!({"name": "editor", "version": "2.1"}));
+        assert_eq!(params["protocolVersion"], "2025-06-18");
+        assert_eq!(params["capabilities"], json!({"roots": {}}));
+
+        let (_, params) = initialize_params(request, None).unwrap();
+        assert_eq!(params["capabilities"], json!({"sampling": {}}));
+    }
+
+    #[test]
+    fn test_bridge_presents_requested_version() {
+        let params = json!({"protocolVersion": "2024-11-05"});
+        let mut result = json!({"protocolVersion": "2025-06-18", "capabilities": {}});
+
+        assert!(bridge(&params, &VersionPolicy::default(), &mut result).is_some());
+        assert_eq!(result["protocolVersion"], "2024-11-05");
+
+        // A version the proxy cannot bridge is left for the client to judge
+        let mut result = json!({"protocolVersion": "2025-06-18"});
+        assert!(bridge(&json!({"protocolVersion": "2023-01-01"}), &VersionPolicy::default(), &mut result).is_none());
+        assert_eq!(result["protocolVersion"], "2025-06-18");
+    }
+
+    #[test]
+    fn test_bridge_passes_older_server_version_through() {
+        let params = json!({"protocolVersion": "2025-06-18"});
+        let mut result = json!({"protocolVersion": "2024-11-05", "capabilities": {}});
+
+        assert!(bridge(&params, &VersionPolicy::default(), &mut result).is_none());
+        assert_eq!(result["protocolVersion"], "2024-11-05");
+    }
+}
@@ -0,0 +1,212 @@
+//! Rewrites the ids of forwarded requests so they cannot collide upstream.
+//!
+//! The remote client numbers its own requests (`initialize`, `tools/list`, ...)
+//! on the same connection the local client's requests are forwarded over. Each
+//! forwarded request is therefore given an id from that client's sequence, and
+//! the local client's id is put back on the response. Progress tokens and
+//! cancellations refer to requests, so they are translated the same way.
+
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use tracing::debug;
+
+/// How many finished requests stay mapped for a cancellation that crosses their response.
+const MAX_RETIRED: usize = 256;
+
+/// Where a local request went upstream.
+struct Route {
+    upstream_id: Value,
+    /// Upstream progress token, when the request asked for progress
+    token: Option<String>,
+}
+
+#[derive(Default)]
+struct State {
+    /// In-flight requests, keyed by the local client's id
+    active: HashMap<String, Route>,
+    /// Original progress tokens, keyed by their upstream replacement
+    tokens: HashMap<String, Value>,
+    /// Recently finished requests, oldest first
+    retired: VecDeque<(String, Value)>,
+}
+
+/// Id translation for one upstream connection.
+#[derive(Default)]
+pub struct IdMapper {
+    state: Mutex<State>,
+}
+
+/// A request rewritten for upstream; dropping it forgets the mapping.
+pub struct MappedRequest<'a> {
+    mapper: &'a IdMapper,
+    key: String,
+    original_id: Value,
+    message: String,
+}
+
+impl MappedRequest<'_> {
+    /// The request as it is sent upstream.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Puts the local client's id back on the upstream response.
+    pub fn restore(&self, response: &str) -> String {
+        match serde_json::from_str::<Value>(response) {
+            Ok(mut parsed) if parsed.get("id").is_some() => {
+                parsed["id"] = self.original_id.clone();
+                parsed.to_string()
+            }
+            _ => response.to_string(),
+        }
+    }
+}
+
+impl Drop for MappedRequest<'_> {
+    fn drop(&mut self) {
+        self.mapper.release(&self.key);
+    }
+}
+
+impl IdMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Rewrites `request` to carry `upstream_id`, along with its progress token if it has one.
+    pub fn map_request(&self, request: &str, upstream_id: Value) -> serde_json::Result<MappedRequest<'_>> {
+        let mut parsed: Value = serde_json::from_str(request)?;
+        let original_id = parsed.get("id").cloned().unwrap_or(Value::Null);
+        let key = original_id.to_string();
+
+        parsed["id"] = upstream_id.clone();
+
+        let mut state = self.state();
+        let token = match parsed.pointer_mut("/params/_meta/progressToken") {
+            Some(token) => {
+                let original_token = std::mem::replace(token, upstream_id.clone());
+                state.tokens.insert(upstream_id.to_string(), original_token);
+                Some(upstream_id.to_string())
+            }
+            None => None,
+        };
+
+        debug!("Forwarding request {} upstream as {}", key, upstream_id);
+        state.active.insert(key.clone(), Route { upstream_id, token });
+
+        Ok(MappedRequest { mapper: self, key, original_id, message: parsed.to_string() })
+    }
+
+    fn release(&self, key: &str) {
+        let mut state = self.state();
+        let Some(route) = state.active.remove(key) else {
+            return;
+        };
+
+        if let Some(token) = route.token {
+            state.tokens.remove(&token);
+        }
+        if state.retired.len() == MAX_RETIRED {
+            state.retired.pop_front();
+        }
+        state.retired.push_back((key.to_string(), route.upstream_id));
+    }
+
+    /// The upstream id of the local request `id`, including one that has just finished.
+    fn upstream_id(&self, id: &Value) -> Option<Value> {
+        let key = id.to_string();
+        let state = self.state();
+        if let Some(route) = state.active.get(&key) {
+            return Some(route.upstream_id.clone());
+        }
+        state.retired.iter().rev()
+            .find(|(retired, _)| *retired == key)
+            .map(|(_, upstream_id)| upstream_id.clone())
+    }
+
+    /// Translates a notification from the local client for upstream.
+    ///
+    /// Returns `None` for a cancellation of a request this connection never
+    /// carried: forwarding it unchanged could cancel an unrelated request that
+    /// happens to have the same upstream id.
+    pub fn map_notification(&self, notification: &str) -> Option<String> {
+        let Ok(mut parsed) = serde_json::from_str::<Value>(notification) else {
+            return Some(notification.to_string());
+        };
+        if parsed.get("method").and_then(Value::as_str) != Some("notifications/cancelled") {
+            return Some(notification.to_string());
+        }
+
+        let request_id = parsed.pointer_mut("/params/requestId")?;
+        *request_id = self.upstream_id(request_id)?;
+        Some(parsed.to_string())
+    }
+
+    /// Restores the local client's progress token in a message from the server.
+    pub fn restore_server_message(&self, message: &str) -> String {
+        let Ok(mut parsed) = serde_json::from_str::<Value>(message) else {
+            return message.to_string();
+        };
+        if parsed.get("method").and_then(Value::as_str) != Some("notifications/progress") {
+            return message.to_string();
+        }
+
+        let Some(token) = parsed.pointer_mut("/params/progressToken") else {
+            return message.to_string();
+        };
+        match self.state().tokens.get(&token.to_string()) {
+            Some(original) => *token = original.clone(),
+            None => return message.to_string(),
+        }
+        parsed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(message: &str) -> Value {
+        serde_json::from_str(message).unwrap()
+    }
+
+    #[test]
+    fn test_maps_ids_and_progress_tokens() {
+        let ids = IdMapper::new();
+        let request = r#"{"jsonrpc":"2.0","id":"a","method":"tools/call","params":{"name":"x","_meta":{"progressToken":"t1"}}}"#;
+
+        let mapped = ids.map_request(request, json!(7)).unwrap();
+        let upstream = parse(mapped.message());
+        assert_eq!(upstream["id"], json!(7));
+        assert_eq!(upstream["params"]["_meta"]["progressToken"], json!(7));
+
+        let progress = ids.restore_server_message(r#"{"jsonrpc":"2.0","method":"notifications/progress","params":{"progressToken":7,"progress":1}}"#);
+        assert_eq!(parse(&progress)["params"]["progressToken"], json!("t1"));
+
+        let response = mapped.restore(r#"{"jsonrpc":"2.0","id":7,"result":{}}"#);
+        assert_eq!(parse(&response)["id"], json!("a"));
+    }
+
+    #[test]
+    fn test_maps_cancellations() {
+        let ids = IdMapper::new();
+        let cancel = |id: Value| json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": id}}).to_string();
+
+        let mapped = ids.map_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#, json!(9)).unwrap();
+        assert_eq!(parse(&ids.map_notification(&cancel(json!(1))).unwrap())["params"]["requestId"], json!(9));
+
+        // Still mapped after completion, for a cancellation that crossed the response
+        drop(mapped);
+        assert!(ids.map_notification(&cancel(json!(1))).is_some());
+
+        assert_eq!(ids.map_notification(&cancel(json!(2))), None);
+        let initialized = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        assert_eq!(ids.map_notification(initialized).as_deref(), Some(initialized));
+    }
+}
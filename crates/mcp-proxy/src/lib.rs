@@ -1,3 +1,6 @@
+mod batch;
+mod handshake;
+pub mod id_map;
 pub mod proxy;
 pub mod stdio_proxy;
 pub mod strategy;
@@ -8,4 +11,5 @@ pub use proxy::McpProxy;
 pub use stdio_proxy::StdioMcpProxy;
 pub use strategy::{ProxyStrategy, ForwardingStrategy, LoadBalancingStrategy};
 pub use error::ProxyError;
+pub use id_map::IdMapper;
 pub use auth_proxy::{AuthenticatedProxy, AuthProxyConfig};
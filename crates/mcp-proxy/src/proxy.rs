@@ -1,7 +1,11 @@
+use crate::batch::{self, BatchHandler, BatchItem};
+use async_trait::async_trait;
 use crate::error::{ProxyError, Result};
-use crate::strategy::ProxyStrategy;
+use crate::strategy::{ForwardingStrategy, ProxyStrategy};
+use mcp_client::InboundReceiver;
+use serde_json::Value;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 use tracing::{debug, error, info};
 
 pub struct McpProxy {
@@ -34,7 +38,48 @@ impl McpProxy {
 
         debug!("Proxy handling message: {}", message);
 
-        match self.strategy.handle_request(message).await {
+        match batch::parse(message) {
+            Some(items) => self.handle_batch(items).await,
+            None => Self::handle_single(&self.strategy, message).await,
+        }
+    }
+
+    /// Handles every message of a batch and replies with one array.
+    ///
+    /// Notifications and responses are forwarded in order; the requests run
+    /// concurrently.
+    async fn handle_batch(&self, items: Vec<BatchItem>) -> Result<Option<String>> {
+        Ok(batch::dispatch(items, self).await.collect().await)
+    }
+
+    async fn handle_single(strategy: &Arc<dyn ProxyStrategy>, message: &str) -> Result<Option<String>> {
+        if ForwardingStrategy::is_notification(message) {
+            return match strategy.handle_notification(message).await {
+                Ok(()) => {
+                    debug!("Proxy forwarded notification");
+                    Ok(None)
+                }
+                Err(e) => {
+                    error!("Proxy error forwarding notification: {}", e);
+                    Err(e)
+                }
+            };
+        }
+
+        if ForwardingStrategy::is_response(message) {
+            return match strategy.handle_response(message).await {
+                Ok(()) => {
+                    debug!("Proxy routed response to server request");
+                    Ok(None)
+                }
+                Err(e) => {
+                    error!("Proxy error routing response: {}", e);
+                    Err(e)
+                }
+            };
+        }
+
+        match strategy.handle_request(message).await {
             Ok(response) => {
                 if let Some(ref resp) = response {
                     debug!("Proxy returning response: {}", resp);
@@ -50,6 +95,11 @@ impl McpProxy {
         }
     }
 
+    /// Takes the stream of messages the remote side sends to the local client unprompted.
+    pub async fn take_server_messages(&self) -> Option<InboundReceiver> {
+        self.strategy.take_server_messages().await
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
         info!("Shutting down MCP proxy");
 
@@ -64,3 +114,26 @@ impl McpProxy {
         *self.running.lock().await
     }
 }
+
+#[async_trait]
+impl BatchHandler for McpProxy {
+    async fn handle_message(&self, message: &str) {
+        if let Err(e) = Self::handle_single(&self.strategy, message).await {
+            error!("Error handling batched message: {}", e);
+        }
+    }
+
+    async fn start_request(&self, _id: Value, message: String, replies: &mpsc::UnboundedSender<String>) {
+        let strategy = self.strategy.clone();
+        let replies = replies.clone();
+        tokio::spawn(async move {
+            let reply = match Self::handle_single(&strategy, &message).await {
+                Ok(reply) => reply,
+                Err(e) => ForwardingStrategy::error_response(&message, &format!("Proxy error: {}", e)),
+            };
+            if let Some(reply) = reply {
+                let _ = replies.send(reply);
+            }
+        });
+    }
+}
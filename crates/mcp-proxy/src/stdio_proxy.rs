@@ -1,68 +1,164 @@
+use crate::batch::{self, BatchHandler, BatchItem};
 use crate::error::{ProxyError, Result};
 use crate::proxy::McpProxy;
 use crate::strategy::ProxyStrategy;
+use async_trait::async_trait;
+use mcp_client::transport::Framing;
 use mcp_types::McpServer;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tracing::{error, info};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::sync::{mpsc, Mutex, Semaphore};
+use tokio::task::AbortHandle;
+use tracing::{debug, error, info, warn};
+
+/// Default cap on requests forwarded concurrently by [`StdioMcpProxy`].
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 32;
+
+/// Requests currently being forwarded, keyed by their JSON-RPC id.
+type InFlight = Arc<Mutex<HashMap<String, AbortHandle>>>;
+
+/// What the tasks forwarding requests share.
+#[derive(Clone)]
+struct Dispatch {
+    /// Bounds how many requests are forwarded at once
+    permits: Arc<Semaphore>,
+    in_flight: InFlight,
+    /// Held by every request task, so the channel closes once the last of them ends
+    running: mpsc::Sender<()>,
+}
+
+/// Runs the messages of a batch the way [`StdioMcpProxy`] runs single ones.
+struct BatchDispatch<'a> {
+    proxy: &'a StdioMcpProxy,
+    dispatch: &'a Dispatch,
+}
+
+#[async_trait]
+impl BatchHandler for BatchDispatch<'_> {
+    async fn handle_message(&self, message: &str) {
+        let cancelled = serde_json::from_str::<Value>(message).ok()
+            .and_then(|parsed| StdioMcpProxy::cancelled_request(&parsed));
+        if let Some(cancelled) = cancelled {
+            StdioMcpProxy::cancel_in_flight(&cancelled, &self.dispatch.in_flight).await;
+        }
+        if let Err(e) = self.proxy.proxy.handle_message(message).await {
+            error!("Error handling batched message: {}", e);
+        }
+    }
+
+    async fn start_request(&self, id: Value, message: String, replies: &mpsc::UnboundedSender<String>) {
+        self.proxy.dispatch_request(id, message, self.dispatch, replies).await;
+    }
+}
 
 pub struct StdioMcpProxy {
-    proxy: McpProxy,
+    proxy: Arc<McpProxy>,
     debug_mode: bool,
+    max_in_flight: usize,
+    /// Framing of the messages exchanged with the local client on stdin and stdout
+    framing: Framing,
 }
 
 impl StdioMcpProxy {
     pub fn new(strategy: Arc<dyn ProxyStrategy>, debug_mode: bool) -> Self {
         Self {
-            proxy: McpProxy::new(strategy),
+            proxy: Arc::new(McpProxy::new(strategy)),
             debug_mode,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            framing: Framing::default(),
         }
     }
 
     pub async fn run(&self) -> Result<()> {
+        self.serve(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Runs the proxy with the local client on `input` and `output`.
+    async fn serve<R, W>(&self, input: R, output: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
         info!("Starting STDIO MCP Proxy");
 
         // Start the proxy
         self.proxy.start().await?;
 
-        // Set up STDIO handling
-        let stdin = tokio::io::stdin();
-        let mut stdout = tokio::io::stdout();
-        let mut reader = BufReader::new(stdin);
-        let mut line = String::new();
+        // Set up STDIO handling. All writes to stdout go through a single task so that
+        // responses and server-initiated messages never interleave.
+        let mut reader = BufReader::new(input);
+
+        let (stdout_tx, stdout_rx) = mpsc::unbounded_channel::<String>();
+        let writer = tokio::spawn(Self::write_stdout(output, stdout_rx, self.framing));
+
+        let server_messages = self.proxy.take_server_messages().await.map(|mut server_rx| {
+            let stdout_tx = stdout_tx.clone();
+            let debug_mode = self.debug_mode;
+            tokio::spawn(async move {
+                while let Some(message) = server_rx.recv().await {
+                    if debug_mode {
+                        eprintln!("DEBUG: Server message: {}", message);
+                    }
+                    if stdout_tx.send(message).is_err() {
+                        break;
+                    }
+                }
+            })
+        });
+
+        // Requests are dispatched on their own tasks so a slow call never blocks the
+        // reader; the semaphore bounds how many are forwarded at once.
+        let (running, mut stopped) = mpsc::channel::<()>(1);
+        let dispatch = Dispatch {
+            permits: Arc::new(Semaphore::new(self.max_in_flight)),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            running,
+        };
 
         info!("STDIO MCP Proxy ready, listening for messages");
 
         loop {
-            line.clear();
-
-            match reader.read_line(&mut line).await {
-                Ok(0) => {
+            match self.framing.read_frame(&mut reader).await {
+                Ok(None) => {
                     info!("EOF reached, shutting down proxy");
                     break;
                 }
-                Ok(_) => {
-                    let trimmed = line.trim();
+                Ok(Some(message)) => {
+                    let trimmed = message.trim();
                     if trimmed.is_empty() {
                         continue;
                     }
 
                     self.log_debug(&format!("Received: {}", trimmed));
 
+                    let parsed = serde_json::from_str::<Value>(trimmed).ok();
+                    if let Some(Value::Array(entries)) = parsed {
+                        self.dispatch_batch(batch::split(entries), &dispatch, &stdout_tx).await;
+                        continue;
+                    }
+
+                    if let Some(cancelled) = parsed.as_ref().and_then(Self::cancelled_request) {
+                        Self::cancel_in_flight(&cancelled, &dispatch.in_flight).await;
+                    }
+
+                    let request_id = parsed
+                        .filter(|parsed| parsed.get("method").is_some())
+                        .and_then(|parsed| parsed.get("id").cloned());
+
+                    if let Some(id) = request_id {
+                        self.dispatch_request(id, trimmed.to_string(), &dispatch, &stdout_tx).await;
+                        continue;
+                    }
+
+                    // Notifications and responses are handled inline to preserve their order
                     match self.proxy.handle_message(trimmed).await {
                         Ok(Some(response)) => {
                             self.log_debug(&format!("Sending: {}", response));
 
-                            if let Err(e) = stdout.write_all(response.as_bytes()).await {
-                                error!("Failed to write response to stdout: {}", e);
-                                break;
-                            }
-                            if let Err(e) = stdout.write_all(b"\n").await {
-                                error!("Failed to write newline to stdout: {}", e);
-                                break;
-                            }
-                            if let Err(e) = stdout.flush().await {
-                                error!("Failed to flush stdout: {}", e);
+                            if stdout_tx.send(response).is_err() {
+                                error!("Failed to write response to stdout");
                                 break;
                             }
                         }
@@ -71,25 +167,6 @@ impl StdioMcpProxy {
                         }
                         Err(e) => {
                             error!("Error handling message: {}", e);
-
-                            // Try to send an error response if we can parse the request ID
-                            if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(trimmed) {
-                                if let Some(id) = parsed.get("id") {
-                                    let error_response = serde_json::json!({
-                                        "jsonrpc": "2.0",
-                                        "id": id,
-                                        "error": {
-                                            "code": -32603,
-                                            "message": format!("Proxy error: {}", e)
-                                        }
-                                    });
-
-                                    let error_str = error_response.to_string();
-                                    let _ = stdout.write_all(error_str.as_bytes()).await;
-                                    let _ = stdout.write_all(b"\n").await;
-                                    let _ = stdout.flush().await;
-                                }
-                            }
                         }
                     }
                 }
@@ -100,12 +177,145 @@ impl StdioMcpProxy {
             }
         }
 
+        // Wait for outstanding requests to finish before tearing down
+        drop(dispatch);
+        let _ = stopped.recv().await;
+
+        // Stop relaying server messages and let the writer drain
+        if let Some(handle) = server_messages {
+            handle.abort();
+        }
+        drop(stdout_tx);
+        let _ = writer.await;
+
         // Shutdown the proxy
         self.proxy.shutdown().await?;
         info!("STDIO MCP Proxy shut down");
         Ok(())
     }
 
+    /// Dispatches each request of a batch like a single one, then writes their
+    /// replies as one array once the last of them completes.
+    async fn dispatch_batch(
+        &self,
+        items: Vec<BatchItem>,
+        dispatch: &Dispatch,
+        stdout_tx: &mpsc::UnboundedSender<String>,
+    ) {
+        let replies = batch::dispatch(items, &BatchDispatch { proxy: self, dispatch }).await;
+
+        let stdout_tx = stdout_tx.clone();
+        tokio::spawn(async move {
+            if let Some(response) = replies.collect().await {
+                let _ = stdout_tx.send(response);
+            }
+        });
+    }
+
+    /// Forwards a request on its own task and sends the reply to `reply_tx` once it completes.
+    async fn dispatch_request(
+        &self,
+        id: Value,
+        request: String,
+        dispatch: &Dispatch,
+        reply_tx: &mpsc::UnboundedSender<String>,
+    ) {
+        let key = id.to_string();
+
+        // Hold the map lock across spawn and insert so a fast task can't remove
+        // its entry before it has been added
+        let mut in_flight_guard = dispatch.in_flight.lock().await;
+        if in_flight_guard.contains_key(&key) {
+            warn!("Request id {} is already in flight", key);
+            let _ = reply_tx.send(Self::error_response(&id, -32600, "Duplicate request id"));
+            return;
+        }
+
+        let proxy = self.proxy.clone();
+        let reply_tx = reply_tx.clone();
+        let in_flight_map = dispatch.in_flight.clone();
+        let permits = dispatch.permits.clone();
+        let running = dispatch.running.clone();
+        let debug_mode = self.debug_mode;
+        let task_key = key.clone();
+
+        let task = tokio::spawn(async move {
+            let _running = running;
+            // Waiting here rather than in the reader keeps it reading, so a
+            // cancellation still gets through while every permit is taken
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return;
+            };
+
+            let reply = match proxy.handle_message(&request).await {
+                Ok(Some(response)) => Some(response),
+                Ok(None) => None,
+                Err(e) => {
+                    error!("Error handling request {}: {}", task_key, e);
+                    Some(Self::error_response(&id, -32603, &format!("Proxy error: {}", e)))
+                }
+            };
+
+            if let Some(reply) = reply {
+                if debug_mode {
+                    eprintln!("DEBUG: Sending: {}", reply);
+                }
+                if reply_tx.send(reply).is_err() {
+                    error!("Failed to write response to stdout");
+                }
+            }
+
+            in_flight_map.lock().await.remove(&task_key);
+        });
+
+        in_flight_guard.insert(key, task.abort_handle());
+    }
+
+    /// The id of the request a `notifications/cancelled` message refers to.
+    fn cancelled_request(message: &Value) -> Option<Value> {
+        if message.get("method").and_then(Value::as_str) != Some("notifications/cancelled") {
+            return None;
+        }
+        message.get("params")?.get("requestId").cloned()
+    }
+
+    /// Aborts the forwarding task for `id`, so it stops waiting and retrying and
+    /// its response is never written. The notification itself is still forwarded
+    /// so the remote server can stop working on the request.
+    async fn cancel_in_flight(id: &Value, in_flight: &InFlight) {
+        match in_flight.lock().await.remove(&id.to_string()) {
+            Some(task) => {
+                task.abort();
+                info!("Cancelled request {}", id);
+            }
+            None => debug!("Cancellation for request {} that is not in flight", id),
+        }
+    }
+
+    fn error_response(id: &Value, code: i64, message: &str) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": code,
+                "message": message
+            }
+        })
+        .to_string()
+    }
+
+    async fn write_stdout<W>(mut stdout: W, mut rx: mpsc::UnboundedReceiver<String>, framing: Framing)
+    where
+        W: AsyncWrite + Unpin,
+    {
+        while let Some(message) = rx.recv().await {
+            if let Err(e) = framing.write_frame(&mut stdout, &message).await {
+                error!("Failed to write message to stdout: {}", e);
+                break;
+            }
+        }
+    }
+
     fn log_debug(&self, message: &str) {
         if self.debug_mode {
             // In debug mode, write to stderr to avoid interfering with stdout protocol
@@ -153,6 +363,8 @@ impl McpServer for CombinedStdioProxy {
 pub struct StdioProxyBuilder {
     strategy: Option<Arc<dyn ProxyStrategy>>,
     debug_mode: bool,
+    max_in_flight: usize,
+    framing: Framing,
 }
 
 impl StdioProxyBuilder {
@@ -160,6 +372,8 @@ impl StdioProxyBuilder {
         Self {
             strategy: None,
             debug_mode: false,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            framing: Framing::default(),
         }
     }
 
@@ -173,18 +387,36 @@ impl StdioProxyBuilder {
         self
     }
 
+    /// Caps how many requests may be forwarded concurrently (at least one).
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Sets how messages from and to the local client are framed on stdin and stdout.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
     pub fn build(self) -> Result<StdioMcpProxy> {
         let strategy = self.strategy
             .ok_or_else(|| ProxyError::Strategy("No strategy provided".to_string()))?;
 
-        Ok(StdioMcpProxy::new(strategy, self.debug_mode))
+        let mut proxy = StdioMcpProxy::new(strategy, self.debug_mode);
+        proxy.max_in_flight = self.max_in_flight;
+        proxy.framing = self.framing;
+        Ok(proxy)
     }
 
     pub fn build_combined(self) -> Result<CombinedStdioProxy> {
         let strategy = self.strategy
             .ok_or_else(|| ProxyError::Strategy("No strategy provided".to_string()))?;
 
-        Ok(CombinedStdioProxy::new(strategy, self.debug_mode))
+        let mut combined = CombinedStdioProxy::new(strategy, self.debug_mode);
+        combined.stdio_proxy.max_in_flight = self.max_in_flight;
+        combined.stdio_proxy.framing = self.framing;
+        Ok(combined)
     }
 }
 
@@ -193,3 +425,135 @@ impl Default for StdioProxyBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use mcp_client::InboundReceiver;
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    /// Answers each request after the `delay_ms` in its params and records notifications.
+    #[derive(Default)]
+    struct DelayStrategy {
+        notifications: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ProxyStrategy for DelayStrategy {
+        async fn handle_request(&self, request: &str) -> Result<Option<String>> {
+            let request: Value = serde_json::from_str(request)?;
+            let delay = request["params"]["delay_ms"].as_u64().unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(Some(json!({"jsonrpc": "2.0", "id": request["id"], "result": {}}).to_string()))
+        }
+
+        async fn handle_notification(&self, notification: &str) -> Result<()> {
+            self.notifications.lock().await.push(notification.to_string());
+            Ok(())
+        }
+
+        async fn handle_response(&self, _response: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn take_server_messages(&self) -> Option<InboundReceiver> {
+            None
+        }
+
+        async fn initialize(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request(id: Value, delay_ms: u64) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "tools/call", "params": {"delay_ms": delay_ms}}).to_string()
+    }
+
+    fn cancel(id: Value) -> String {
+        json!({"jsonrpc": "2.0", "method": "notifications/cancelled", "params": {"requestId": id}}).to_string()
+    }
+
+    /// Feeds `lines` to a proxy until EOF and returns what it wrote, in order.
+    async fn run_proxy(strategy: Arc<DelayStrategy>, max_in_flight: usize, lines: &[String]) -> Vec<Value> {
+        let proxy = StdioProxyBuilder::new()
+            .with_strategy(strategy)
+            .with_max_in_flight(max_in_flight)
+            .build()
+            .unwrap();
+
+        let input = lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+        let (output, mut client) = tokio::io::duplex(64 * 1024);
+        tokio::time::timeout(Duration::from_secs(5), proxy.serve(input.as_bytes(), output))
+            .await
+            .expect("proxy did not shut down")
+            .unwrap();
+
+        let mut written = String::new();
+        client.read_to_string(&mut written).await.unwrap();
+        written.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_replies_in_completion_order() {
+        let strategy = Arc::new(DelayStrategy::default());
+        let replies = run_proxy(strategy, 4, &[request(json!(1), 200), request(json!(2), 0)]).await;
+
+        let ids: Vec<&Value> = replies.iter().map(|reply| &reply["id"]).collect();
+        assert_eq!(ids, [&json!(2), &json!(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_duplicate_in_flight_id() {
+        let strategy = Arc::new(DelayStrategy::default());
+        let replies = run_proxy(strategy, 4, &[request(json!("a"), 200), request(json!("a"), 0)]).await;
+
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0]["id"], "a");
+        assert_eq!(replies[0]["error"]["code"], -32600);
+        assert_eq!(replies[1]["result"], json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_aborts_only_its_request() {
+        let strategy = Arc::new(DelayStrategy::default());
+        let lines = [request(json!(1), 10_000), request(json!(2), 100), cancel(json!(1))];
+        let replies = run_proxy(strategy.clone(), 4, &lines).await;
+
+        assert_eq!(replies, [json!({"jsonrpc": "2.0", "id": 2, "result": {}})]);
+        // The server still hears about it
+        assert_eq!(strategy.notifications.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_reaches_a_full_dispatcher() {
+        let strategy = Arc::new(DelayStrategy::default());
+        // Both permits are held by requests that would outlast the test, and the
+        // cancellations arrive behind a request still waiting for one
+        let lines = [
+            request(json!(1), 10_000),
+            request(json!(2), 10_000),
+            request(json!(3), 0),
+            cancel(json!(1)),
+            cancel(json!(2)),
+        ];
+        let replies = run_proxy(strategy, 2, &lines).await;
+
+        assert_eq!(replies, [json!({"jsonrpc": "2.0", "id": 3, "result": {}})]);
+    }
+
+    #[tokio::test]
+    async fn test_drains_requests_on_shutdown() {
+        let strategy = Arc::new(DelayStrategy::default());
+        // EOF follows right away, while both are still in flight
+        let replies = run_proxy(strategy, 4, &[request(json!(1), 300), request(json!(2), 100)]).await;
+
+        assert_eq!(replies.len(), 2);
+    }
+}
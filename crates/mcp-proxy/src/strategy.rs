@@ -1,45 +1,96 @@
 use crate::error::{ProxyError, Result};
+use crate::handshake;
+use crate::id_map::IdMapper;
 use async_trait::async_trait;
-use mcp_client::McpRemoteClient;
+use mcp_client::{InboundReceiver, McpRemoteClient, Shims};
 use mcp_types::McpClient;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 
 #[async_trait]
 pub trait ProxyStrategy: Send + Sync {
     async fn handle_request(&self, request: &str) -> Result<Option<String>>;
+    async fn handle_notification(&self, notification: &str) -> Result<()>;
+    /// Routes the local client's reply to a server-initiated request back upstream.
+    async fn handle_response(&self, response: &str) -> Result<()>;
+    /// Takes the stream of requests and notifications initiated by the remote server(s).
+    async fn take_server_messages(&self) -> Option<InboundReceiver>;
     async fn initialize(&self) -> Result<()>;
     async fn shutdown(&self) -> Result<()>;
 }
 
 pub struct ForwardingStrategy {
-    client: Arc<Mutex<McpRemoteClient>>,
-    initialized: Arc<Mutex<bool>>,
+    client: Arc<RwLock<McpRemoteClient>>,
+    /// The remote server's `InitializeResult`, once the handshake is done
+    initialized: Arc<Mutex<Option<Value>>>,
+    /// JSON merge patch applied to the local client's capabilities
+    capability_overrides: Option<Value>,
+    ids: Arc<IdMapper>,
+    /// Set when the local client speaks an older protocol version than the server
+    shims: Arc<RwLock<Option<Shims>>>,
 }
 
 impl ForwardingStrategy {
     pub fn new(client: McpRemoteClient) -> Self {
         Self {
-            client: Arc::new(Mutex::new(client)),
-            initialized: Arc::new(Mutex::new(false)),
+            client: Arc::new(RwLock::new(client)),
+            initialized: Arc::new(Mutex::new(None)),
+            capability_overrides: None,
+            ids: Arc::new(IdMapper::new()),
+            shims: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Merges `overrides` into the capabilities the local client declares in `initialize`.
+    pub fn with_capability_overrides(mut self, overrides: Value) -> Self {
+        self.capability_overrides = Some(overrides);
+        self
+    }
+
+    /// Initializes the remote server once, with the local client's `params` if it
+    /// sent any, and returns the server's result.
+    async fn handshake(&self, params: Option<Value>) -> Result<Value> {
+        // Held for the whole handshake so concurrent requests don't initialize twice
+        let mut initialized = self.initialized.lock().await;
+        if let Some(result) = initialized.as_ref() {
+            return Ok(result.clone());
+        }
+
+        let client = self.client.read().await;
+        let result = match params {
+            Some(params) => {
+                let mut result = client.initialize_with(params.clone()).await?;
+                *self.shims.write().await = handshake::bridge(&params, client.version_policy(), &mut result);
+                result
+            }
+            // A client that skipped initialize gets the proxy's own handshake
+            None => serde_json::to_value(client.initialize().await?)?,
+        };
+        *initialized = Some(result.clone());
+        info!("Proxy client initialized successfully");
+        Ok(result)
+    }
+
     async fn ensure_initialized(&self) -> Result<()> {
-        let initialized = *self.initialized.lock().await;
-        if !initialized {
-            let mut client = self.client.lock().await;
-            client.connect().await?;
-            let _init_result = client.initialize().await?;
-            *self.initialized.lock().await = true;
-            info!("Proxy client initialized successfully");
+        self.handshake(None).await.map(|_| ())
+    }
+
+    /// Answers the local client's `initialize` with the remote server's result.
+    async fn relay_initialize(&self, request: &str) -> Result<Option<String>> {
+        let (id, params) = handshake::initialize_params(request, self.capability_overrides.as_ref())?;
+        match self.handshake(Some(params)).await {
+            Ok(result) => Ok(Some(handshake::reply(&id, &result))),
+            Err(e) => {
+                error!("Failed to initialize remote server: {}", e);
+                Ok(Self::error_response(request, &format!("Proxy forwarding error: {}", e)))
+            }
         }
-        Ok(())
     }
 
-    fn is_notification(message: &str) -> bool {
+    pub(crate) fn is_notification(message: &str) -> bool {
         if let Ok(parsed) = serde_json::from_str::<Value>(message) {
             parsed.get("id").is_none() && parsed.get("method").is_some()
         } else {
@@ -47,12 +98,69 @@ impl ForwardingStrategy {
         }
     }
 
+    pub(crate) fn is_response(message: &str) -> bool {
+        if let Ok(parsed) = serde_json::from_str::<Value>(message) {
+            parsed.get("id").is_some()
+                && parsed.get("method").is_none()
+                && (parsed.get("result").is_some() || parsed.get("error").is_some())
+        } else {
+            false
+        }
+    }
+
+    /// Builds a JSON-RPC internal error reply to `request`, if it has an id.
+    pub(crate) fn error_response(request: &str, message: &str) -> Option<String> {
+        let parsed = serde_json::from_str::<Value>(request).ok()?;
+        let error_response = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": parsed.get("id")?,
+            "error": {
+                "code": -32603,
+                "message": message
+            }
+        });
+        Some(error_response.to_string())
+    }
+
     fn extract_method(message: &str) -> Option<String> {
         serde_json::from_str::<Value>(message)
             .ok()
             .and_then(|v| v.get("method").cloned())
             .and_then(|m| m.as_str().map(|s| s.to_string()))
     }
+
+    /// Forwards `request` under an id from `client`'s own sequence and restores
+    /// the original on the reply, adapted to the local client's protocol version.
+    async fn send_mapped(
+        client: &McpRemoteClient,
+        ids: &IdMapper,
+        shims: &RwLock<Option<Shims>>,
+        request: &str,
+    ) -> mcp_client::error::Result<String> {
+        let upstream_id = Value::from(client.next_request_id().await);
+        let mapped = ids.map_request(request, upstream_id)?;
+
+        let response = client.send_request_with_retry(mapped.message()).await?;
+        let response = mapped.restore(&response);
+        Ok(match (shims.read().await.as_ref(), Self::extract_method(request)) {
+            (Some(shims), Some(method)) => shims.adapt_response(&method, &response),
+            _ => response,
+        })
+    }
+
+    /// Answers a server request the local client's protocol version has no
+    /// counterpart for, returning true if it was handled here.
+    async fn reject_server_request(client: &RwLock<McpRemoteClient>, shims: &RwLock<Option<Shims>>, message: &str) -> bool {
+        let Some(reply) = shims.read().await.as_ref().and_then(|shims| shims.reject_server_request(message)) else {
+            return false;
+        };
+
+        debug!("Declining server request the local client cannot handle: {}", message);
+        if let Err(e) = client.read().await.send_response(&reply).await {
+            warn!("Failed to decline server request: {}", e);
+        }
+        true
+    }
 }
 
 #[async_trait]
@@ -63,17 +171,22 @@ impl ProxyStrategy for ForwardingStrategy {
         // Check if it's a notification (no response expected)
         if Self::is_notification(request) {
             debug!("Received notification, forwarding without expecting response");
-            // For notifications, we might want to forward them but don't expect a response
+            self.handle_notification(request).await?;
             return Ok(None);
         }
 
+        if handshake::is_initialize(request) {
+            return self.relay_initialize(request).await;
+        }
+
         self.ensure_initialized().await?;
 
         let method = Self::extract_method(request);
         debug!("Extracted method: {:?}", method);
 
-        let mut client = self.client.lock().await;
-        match client.send_request(request).await {
+        // A read guard lets any number of requests share the client concurrently
+        let client = self.client.read().await;
+        match Self::send_mapped(&client, &self.ids, &self.shims, request).await {
             Ok(response) => {
                 debug!("Received response: {}", response);
                 Ok(Some(response))
@@ -82,76 +195,230 @@ impl ProxyStrategy for ForwardingStrategy {
                 error!("Failed to forward request: {}", e);
 
                 // Create an error response in JSON-RPC format
-                if let Ok(parsed) = serde_json::from_str::<Value>(request) {
-                    if let Some(id) = parsed.get("id") {
-                        let error_response = serde_json::json!({
-                            "jsonrpc": "2.0",
-                            "id": id,
-                            "error": {
-                                "code": -32603,
-                                "message": format!("Proxy forwarding error: {}", e)
-                            }
-                        });
-                        return Ok(Some(error_response.to_string()));
-                    }
+                match Self::error_response(request, &format!("Proxy forwarding error: {}", e)) {
+                    Some(error_response) => Ok(Some(error_response)),
+                    None => Err(ProxyError::ForwardingFailed(e.to_string())),
                 }
-
-                Err(ProxyError::ForwardingFailed(e.to_string()))
             }
         }
     }
 
+    async fn handle_notification(&self, notification: &str) -> Result<()> {
+        debug!("Forwarding notification: {:?}", Self::extract_method(notification));
+
+        self.ensure_initialized().await?;
+
+        let Some(notification) = self.ids.map_notification(notification) else {
+            debug!("Dropping cancellation of a request that was never forwarded");
+            return Ok(());
+        };
+
+        let client = self.client.read().await;
+        client.send_notification(&notification).await.map_err(|e| {
+            error!("Failed to forward notification: {}", e);
+            ProxyError::ForwardingFailed(e.to_string())
+        })
+    }
+
+    async fn handle_response(&self, response: &str) -> Result<()> {
+        debug!("Forwarding response to server request: {}", response);
+
+        let client = self.client.read().await;
+        client.send_response(response).await.map_err(|e| {
+            error!("Failed to forward response: {}", e);
+            ProxyError::ForwardingFailed(e.to_string())
+        })
+    }
+
+    async fn take_server_messages(&self) -> Option<InboundReceiver> {
+        let mut client_rx = self.client.read().await.take_inbound_receiver().await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let client = self.client.clone();
+        let ids = self.ids.clone();
+        let shims = self.shims.clone();
+        tokio::spawn(async move {
+            while let Some(message) = client_rx.recv().await {
+                let message = ids.restore_server_message(&message);
+                if Self::reject_server_request(&client, &shims, &message).await {
+                    continue;
+                }
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Some(rx)
+    }
+
     async fn initialize(&self) -> Result<()> {
-        self.ensure_initialized().await
+        // Only connect: the handshake waits for the local client's own initialize
+        self.client.write().await.connect().await?;
+        Ok(())
     }
 
     async fn shutdown(&self) -> Result<()> {
-        let mut client = self.client.lock().await;
+        let mut client = self.client.write().await;
         client.disconnect().await?;
-        *self.initialized.lock().await = false;
+        *self.initialized.lock().await = None;
+        *self.shims.write().await = None;
         info!("Proxy strategy shut down");
         Ok(())
     }
 }
 
+/// The local client's side of the handshake, replayed to each client as it is initialized.
+#[derive(Default)]
+struct Handshake {
+    /// The local client's `initialize` params, overrides applied
+    params: Option<Value>,
+    /// The first server's `InitializeResult`, which is what the local client sees
+    result: Option<Value>,
+    /// The local client's `notifications/initialized`
+    initialized: Option<String>,
+}
+
 pub struct LoadBalancingStrategy {
-    clients: Vec<Arc<Mutex<McpRemoteClient>>>,
+    clients: Vec<Arc<RwLock<McpRemoteClient>>>,
+    /// Id translation for each client's connection
+    ids: Vec<Arc<IdMapper>>,
+    /// Protocol shims for each client's connection
+    shims: Vec<Arc<RwLock<Option<Shims>>>>,
     current_client: Arc<Mutex<usize>>,
+    /// Always locked before `handshake`; neither is held across a network call
     initialized: Arc<Mutex<Vec<bool>>>,
+    handshake: Arc<Mutex<Handshake>>,
+    /// Held while a client is being initialized, so a slow or dead server only
+    /// holds up requests waiting on that same client
+    initializing: Vec<Mutex<()>>,
+    /// JSON merge patch applied to the local client's capabilities
+    capability_overrides: Option<Value>,
+    /// Which client each pending server-initiated request came from and its
+    /// original id, keyed by the id the local client sees
+    server_requests: Arc<Mutex<HashMap<String, (usize, Value)>>>,
 }
 
 impl LoadBalancingStrategy {
     pub fn new(clients: Vec<McpRemoteClient>) -> Self {
         let client_count = clients.len();
         Self {
-            clients: clients.into_iter().map(|c| Arc::new(Mutex::new(c))).collect(),
+            clients: clients.into_iter().map(|c| Arc::new(RwLock::new(c))).collect(),
+            ids: (0..client_count).map(|_| Arc::new(IdMapper::new())).collect(),
+            shims: (0..client_count).map(|_| Arc::new(RwLock::new(None))).collect(),
             current_client: Arc::new(Mutex::new(0)),
             initialized: Arc::new(Mutex::new(vec![false; client_count])),
+            handshake: Arc::new(Mutex::new(Handshake::default())),
+            initializing: (0..client_count).map(|_| Mutex::new(())).collect(),
+            capability_overrides: None,
+            server_requests: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    async fn get_next_client(&self) -> Result<Arc<Mutex<McpRemoteClient>>> {
+    /// Merges `overrides` into the capabilities the local client declares in `initialize`.
+    pub fn with_capability_overrides(mut self, overrides: Value) -> Self {
+        self.capability_overrides = Some(overrides);
+        self
+    }
+
+    async fn get_next_client(&self) -> Result<(usize, Arc<RwLock<McpRemoteClient>>)> {
         let mut current = self.current_client.lock().await;
-        let client = self.clients.get(*current)
+        let client_index = *current;
+        let client = self.clients.get(client_index)
             .ok_or_else(|| ProxyError::Strategy("No clients available".to_string()))?
             .clone();
 
         *current = (*current + 1) % self.clients.len();
-        Ok(client)
+        Ok((client_index, client))
     }
 
     async fn ensure_client_initialized(&self, client_index: usize) -> Result<()> {
-        let mut initialized = self.initialized.lock().await;
-        if !initialized[client_index] {
-            let client = &self.clients[client_index];
-            let mut client_guard = client.lock().await;
-            client_guard.connect().await?;
-            let _init_result = client_guard.initialize().await?;
+        if self.initialized.lock().await[client_index] {
+            return Ok(());
+        }
+
+        let _initializing = self.initializing[client_index].lock().await;
+        if self.initialized.lock().await[client_index] {
+            return Ok(()); // Another request got there first
+        }
+
+        let params = self.handshake.lock().await.params.clone();
+        let client_guard = self.clients[client_index].read().await;
+        let result = match params {
+            Some(params) => {
+                let mut result = client_guard.initialize_with(params.clone()).await?;
+                *self.shims[client_index].write().await = handshake::bridge(&params, client_guard.version_policy(), &mut result);
+                result
+            }
+            None => serde_json::to_value(client_guard.initialize().await?)?,
+        };
+
+        // Marked under both locks, so `handle_notification` either sends this client
+        // a later `initialized` itself or leaves it recorded for us to send
+        let notification = {
+            let mut initialized = self.initialized.lock().await;
+            let mut session = self.handshake.lock().await;
+            session.result.get_or_insert(result);
             initialized[client_index] = true;
-            info!("Load balancing client {} initialized", client_index);
+            session.initialized.clone()
+        };
+
+        // Clients brought in after the handshake completed still need to hear it did
+        if let Some(notification) = notification {
+            if let Err(e) = client_guard.send_notification(&notification).await {
+                self.initialized.lock().await[client_index] = false;
+                return Err(ProxyError::ForwardingFailed(e.to_string()));
+            }
         }
+
+        info!("Load balancing client {} initialized", client_index);
         Ok(())
     }
+
+    /// Answers the local client's `initialize` with the result of the first server to accept it.
+    async fn relay_initialize(&self, request: &str) -> Result<Option<String>> {
+        let (id, params) = handshake::initialize_params(request, self.capability_overrides.as_ref())?;
+        self.handshake.lock().await.params.get_or_insert(params);
+
+        for _ in 0..self.clients.len() {
+            let (client_index, _) = self.get_next_client().await?;
+            match self.ensure_client_initialized(client_index).await {
+                Ok(()) => {
+                    let result = self.handshake.lock().await.result.clone().unwrap_or(Value::Null);
+                    return Ok(Some(handshake::reply(&id, &result)));
+                }
+                Err(e) => warn!("Failed to initialize client {}: {}", client_index, e),
+            }
+        }
+
+        match ForwardingStrategy::error_response(request, "All load-balanced clients failed") {
+            Some(error_response) => Ok(Some(error_response)),
+            None => Err(ProxyError::ForwardingFailed("All clients failed".to_string())),
+        }
+    }
+
+    /// The id the local client sees for a request from client `index`; servers
+    /// number their requests independently, so their ids alone can collide.
+    fn server_request_id(index: usize, id: &Value) -> Value {
+        Value::String(format!("{}:{}", index, id))
+    }
+
+    /// Gives a server-initiated request, or the server's cancellation of one, the id the local client sees.
+    fn map_server_message(index: usize, message: &str) -> Option<(Value, Value, String)> {
+        let mut parsed = serde_json::from_str::<Value>(message).ok()?;
+        let method = parsed.get("method")?.as_str()?;
+
+        let id = if method == "notifications/cancelled" {
+            parsed.pointer_mut("/params/requestId")?
+        } else {
+            parsed.get_mut("id")?
+        };
+        let original = std::mem::replace(id, Value::Null);
+        let local = Self::server_request_id(index, &original);
+        *id = local.clone();
+
+        Some((local, original, parsed.to_string()))
+    }
 }
 
 #[async_trait]
@@ -161,25 +428,34 @@ impl ProxyStrategy for LoadBalancingStrategy {
 
         if ForwardingStrategy::is_notification(request) {
             debug!("Received notification, no response expected");
+            self.handle_notification(request).await?;
             return Ok(None);
         }
 
+        if handshake::is_initialize(request) {
+            return self.relay_initialize(request).await;
+        }
+
         // Try each client until one succeeds
         for _i in 0..self.clients.len() {
-            let client = self.get_next_client().await?;
-            let client_index = {
-                let current = self.current_client.lock().await;
-                (*current + self.clients.len() - 1) % self.clients.len()
-            };
+            let (client_index, client) = self.get_next_client().await?;
 
             match self.ensure_client_initialized(client_index).await {
                 Ok(()) => {
-                    let mut client_guard = client.lock().await;
-                    match client_guard.send_request(request).await {
+                    let client_guard = client.read().await;
+                    match ForwardingStrategy::send_mapped(&client_guard, &self.ids[client_index], &self.shims[client_index], request).await {
                         Ok(response) => {
                             debug!("Client {} handled request successfully", client_index);
                             return Ok(Some(response));
                         }
+                        Err(e) if !e.is_retryable() => {
+                            // The server already has the request; another client would run it again
+                            error!("Client {} lost the response: {}", client_index, e);
+                            return match ForwardingStrategy::error_response(request, &format!("Proxy forwarding error: {}", e)) {
+                                Some(error_response) => Ok(Some(error_response)),
+                                None => Err(ProxyError::ForwardingFailed(e.to_string())),
+                            };
+                        }
                         Err(e) => {
                             warn!("Client {} failed: {}", client_index, e);
                             // Mark client as not initialized to force reconnection
@@ -196,34 +472,113 @@ impl ProxyStrategy for LoadBalancingStrategy {
         }
 
         // All clients failed
-        if let Ok(parsed) = serde_json::from_str::<Value>(request) {
-            if let Some(id) = parsed.get("id") {
-                let error_response = serde_json::json!({
-                    "jsonrpc": "2.0",
-                    "id": id,
-                    "error": {
-                        "code": -32603,
-                        "message": "All load-balanced clients failed"
-                    }
-                });
-                return Ok(Some(error_response.to_string()));
+        match ForwardingStrategy::error_response(request, "All load-balanced clients failed") {
+            Some(error_response) => Ok(Some(error_response)),
+            None => Err(ProxyError::ForwardingFailed("All clients failed".to_string())),
+        }
+    }
+
+    async fn handle_notification(&self, notification: &str) -> Result<()> {
+        // Lifecycle notifications concern every session, so send to all initialized clients
+        let initialized = {
+            let initialized = self.initialized.lock().await;
+            if handshake::is_initialized_notification(notification) {
+                self.handshake.lock().await.initialized = Some(notification.to_string());
+            }
+            initialized.clone()
+        };
+        let mut delivered = false;
+
+        for (i, client) in self.clients.iter().enumerate() {
+            if !initialized[i] {
+                continue;
             }
+
+            // A cancellation only goes to the client that carried the request
+            let Some(notification) = self.ids[i].map_notification(notification) else {
+                continue;
+            };
+
+            let client_guard = client.read().await;
+            match client_guard.send_notification(&notification).await {
+                Ok(()) => delivered = true,
+                Err(e) => warn!("Client {} failed to forward notification: {}", i, e),
+            }
+        }
+
+        if delivered {
+            Ok(())
+        } else {
+            Err(ProxyError::ForwardingFailed("No client accepted the notification".to_string()))
+        }
+    }
+
+    async fn handle_response(&self, response: &str) -> Result<()> {
+        let mut parsed = serde_json::from_str::<Value>(response)?;
+        let id = parsed.get("id")
+            .map(|id| id.to_string())
+            .ok_or_else(|| ProxyError::InvalidMessage("Response without id".to_string()))?;
+
+        let (client_index, original_id) = self.server_requests.lock().await.remove(&id)
+            .ok_or_else(|| ProxyError::ForwardingFailed(format!("No pending server request with id {}", id)))?;
+        parsed["id"] = original_id;
+
+        let client_guard = self.clients[client_index].read().await;
+        client_guard.send_response(&parsed.to_string()).await
+            .map_err(|e| ProxyError::ForwardingFailed(e.to_string()))
+    }
+
+    async fn take_server_messages(&self) -> Option<InboundReceiver> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut taken = false;
+
+        for (i, client) in self.clients.iter().enumerate() {
+            let Some(mut client_rx) = client.read().await.take_inbound_receiver().await else {
+                continue;
+            };
+            taken = true;
+
+            let tx = tx.clone();
+            let client = client.clone();
+            let ids = self.ids[i].clone();
+            let shims = self.shims[i].clone();
+            let server_requests = self.server_requests.clone();
+            tokio::spawn(async move {
+                while let Some(message) = client_rx.recv().await {
+                    let mut message = ids.restore_server_message(&message);
+                    if ForwardingStrategy::reject_server_request(&client, &shims, &message).await {
+                        continue;
+                    }
+
+                    // Remember where requests came from so the reply can be routed back
+                    if let Some((local, original, mapped)) = Self::map_server_message(i, &message) {
+                        if !ForwardingStrategy::is_notification(&mapped) {
+                            server_requests.lock().await.insert(local.to_string(), (i, original));
+                        }
+                        message = mapped;
+                    }
+
+                    if tx.send(message).is_err() {
+                        break;
+                    }
+                }
+            });
         }
 
-        Err(ProxyError::ForwardingFailed("All clients failed".to_string()))
+        taken.then_some(rx)
     }
 
     async fn initialize(&self) -> Result<()> {
-        // Initialize first client immediately, others on-demand
-        if !self.clients.is_empty() {
-            self.ensure_client_initialized(0).await?;
+        // Connect the first client immediately; handshakes wait for the local client's initialize
+        if let Some(client) = self.clients.first() {
+            client.write().await.connect().await?;
         }
         Ok(())
     }
 
     async fn shutdown(&self) -> Result<()> {
         for (i, client) in self.clients.iter().enumerate() {
-            let mut client_guard = client.lock().await;
+            let mut client_guard = client.write().await;
             if let Err(e) = client_guard.disconnect().await {
                 warn!("Error disconnecting client {}: {}", i, e);
             }
@@ -234,3 +589,245 @@ impl ProxyStrategy for LoadBalancingStrategy {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_client::transport::TransportConfig;
+    use mcp_types::TransportType;
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A remote server on TCP that reports every message it receives and, once
+    /// initialized, sends `push` to the client.
+    struct MockServer {
+        endpoint: String,
+        received: mpsc::UnboundedReceiver<Value>,
+    }
+
+    impl MockServer {
+        async fn start(protocol_version: &'static str, push: Vec<Value>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = listener.local_addr().unwrap().to_string();
+            let (received_tx, received) = mpsc::unbounded_channel();
+
+            tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let message: Value = serde_json::from_str(&line).unwrap();
+                    let _ = received_tx.send(message.clone());
+
+                    let replies = match (message.get("method").and_then(Value::as_str), message.get("id")) {
+                        (Some("initialize"), Some(id)) => vec![json!({"jsonrpc": "2.0", "id": id, "result": {
+                            "protocolVersion": protocol_version,
+                            "capabilities": {},
+                            "serverInfo": {"name": "mock", "version": "1.0"}
+                        }})],
+                        (Some("notifications/initialized"), None) => push.clone(),
+                        (Some(_), Some(id)) => vec![json!({"jsonrpc": "2.0", "id": id, "result": {}})],
+                        _ => Vec::new(),
+                    };
+                    for reply in replies {
+                        writer.write_all(format!("{}\n", reply).as_bytes()).await.unwrap();
+                    }
+                }
+            });
+
+            Self { endpoint, received }
+        }
+
+        async fn client(&self) -> McpRemoteClient {
+            let config = TransportConfig { endpoint: self.endpoint.clone(), retry_attempts: 1, ..Default::default() };
+            McpRemoteClient::with_custom_transports(vec![(TransportType::Tcp, config)]).await
+        }
+
+        /// Waits for the first received message matching `matches`.
+        async fn expect(&mut self, matches: impl Fn(&Value) -> bool) -> Value {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let message = self.received.recv().await.expect("mock server stopped");
+                    if matches(&message) {
+                        return message;
+                    }
+                }
+            })
+            .await
+            .expect("message never reached the mock server")
+        }
+    }
+
+    fn method(name: &'static str) -> impl Fn(&Value) -> bool {
+        move |message| message["method"] == name
+    }
+
+    fn initialize(protocol_version: &str) -> String {
+        json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {
+            "protocolVersion": protocol_version,
+            "capabilities": {},
+            "clientInfo": {"name": "editor", "version": "1.0"}
+        }})
+        .to_string()
+    }
+
+    const INITIALIZED: &str = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+
+    async fn next_message(server_rx: &mut InboundReceiver) -> Value {
+        let message = tokio::time::timeout(Duration::from_secs(5), server_rx.recv())
+            .await
+            .expect("no server message reached the local client")
+            .unwrap();
+        serde_json::from_str(&message).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_forwarding_relays_notifications() {
+        let changed = json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"});
+        let mut server = MockServer::start("2025-03-26", vec![changed.clone()]).await;
+        let strategy = ForwardingStrategy::new(server.client().await);
+        strategy.initialize().await.unwrap();
+        let mut server_rx = strategy.take_server_messages().await.unwrap();
+
+        strategy.handle_request(&initialize("2025-03-26")).await.unwrap();
+        strategy.handle_notification(INITIALIZED).await.unwrap();
+
+        server.expect(method("notifications/initialized")).await;
+        assert_eq!(next_message(&mut server_rx).await, changed);
+    }
+
+    #[tokio::test]
+    async fn test_load_balancing_relays_notifications() {
+        let changed = json!({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"});
+        let mut first = MockServer::start("2025-03-26", vec![changed.clone()]).await;
+        let mut second = MockServer::start("2025-03-26", vec![changed.clone()]).await;
+        let strategy = LoadBalancingStrategy::new(vec![first.client().await, second.client().await]);
+        strategy.initialize().await.unwrap();
+        let mut server_rx = strategy.take_server_messages().await.unwrap();
+
+        strategy.handle_request(&initialize("2025-03-26")).await.unwrap();
+        strategy.handle_notification(INITIALIZED).await.unwrap();
+        first.expect(method("notifications/initialized")).await;
+
+        // The second client is initialized on its first request and hears the notification then
+        strategy.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).await.unwrap();
+        second.expect(method("notifications/initialized")).await;
+
+        assert_eq!(next_message(&mut server_rx).await, changed);
+        assert_eq!(next_message(&mut server_rx).await, changed);
+    }
+
+    #[tokio::test]
+    async fn test_load_balancing_dead_backend_does_not_block_others() {
+        let healthy = MockServer::start("2025-03-26", Vec::new()).await;
+
+        // Accepts connections but never answers
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = TransportConfig {
+            endpoint: silent.local_addr().unwrap().to_string(),
+            timeout: Duration::from_secs(2),
+            retry_attempts: 1,
+            ..Default::default()
+        };
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = silent.accept().await {
+                connections.push(stream);
+            }
+        });
+        let dead = McpRemoteClient::with_custom_transports(vec![(TransportType::Tcp, config)]).await;
+
+        let strategy = Arc::new(LoadBalancingStrategy::new(vec![healthy.client().await, dead]));
+        strategy.initialize().await.unwrap();
+        strategy.handle_request(&initialize("2025-03-26")).await.unwrap();
+
+        // Next in turn is the dead backend, whose handshake hangs until it times out
+        let stuck = {
+            let strategy = strategy.clone();
+            tokio::spawn(async move { strategy.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let reply = tokio::time::timeout(
+            Duration::from_millis(500),
+            strategy.handle_request(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#),
+        )
+        .await
+        .expect("request to the healthy backend waited on the dead one")
+        .unwrap()
+        .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&reply).unwrap()["id"], 2);
+        assert!(!stuck.is_finished());
+        stuck.abort();
+    }
+
+    #[tokio::test]
+    async fn test_forwarding_routes_server_requests() {
+        let roots = json!({"jsonrpc": "2.0", "id": "s1", "method": "roots/list"});
+        let mut server = MockServer::start("2025-03-26", vec![roots.clone()]).await;
+        let strategy = ForwardingStrategy::new(server.client().await);
+        strategy.initialize().await.unwrap();
+        let mut server_rx = strategy.take_server_messages().await.unwrap();
+
+        strategy.handle_request(&initialize("2025-03-26")).await.unwrap();
+        strategy.handle_notification(INITIALIZED).await.unwrap();
+        assert_eq!(next_message(&mut server_rx).await, roots);
+
+        strategy.handle_response(r#"{"jsonrpc":"2.0","id":"s1","result":{"roots":[]}}"#).await.unwrap();
+        let response = server.expect(|message| message.get("result").is_some()).await;
+        assert_eq!(response["id"], "s1");
+    }
+
+    #[tokio::test]
+    async fn test_forwarding_declines_requests_the_client_predates() {
+        let elicitation = json!({"jsonrpc": "2.0", "id": 9, "method": "elicitation/create", "params": {}});
+        let log = json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {"level": "info", "data": "ok"}});
+        let mut server = MockServer::start("2025-06-18", vec![elicitation, log.clone()]).await;
+        let strategy = ForwardingStrategy::new(server.client().await);
+        strategy.initialize().await.unwrap();
+        let mut server_rx = strategy.take_server_messages().await.unwrap();
+
+        strategy.handle_request(&initialize("2024-11-05")).await.unwrap();
+        strategy.handle_notification(INITIALIZED).await.unwrap();
+
+        let declined = server.expect(|message| message.get("error").is_some()).await;
+        assert_eq!(declined["id"], 9);
+        assert_eq!(declined["error"]["code"], -32601);
+        // The elicitation never reaches the local client
+        assert_eq!(next_message(&mut server_rx).await, log);
+    }
+
+    #[tokio::test]
+    async fn test_load_balancing_routes_server_requests_by_backend() {
+        // Both servers number their requests alike
+        let roots = json!({"jsonrpc": "2.0", "id": 7, "method": "roots/list"});
+        let mut first = MockServer::start("2025-03-26", vec![roots.clone()]).await;
+        let mut second = MockServer::start("2025-03-26", vec![roots]).await;
+        let strategy = LoadBalancingStrategy::new(vec![first.client().await, second.client().await]);
+        strategy.initialize().await.unwrap();
+        let mut server_rx = strategy.take_server_messages().await.unwrap();
+
+        strategy.handle_request(&initialize("2025-03-26")).await.unwrap();
+        strategy.handle_notification(INITIALIZED).await.unwrap();
+        strategy.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).await.unwrap();
+
+        let mut ids = vec![next_message(&mut server_rx).await["id"].clone()];
+        ids.push(next_message(&mut server_rx).await["id"].clone());
+        ids.sort_by_key(ToString::to_string);
+        assert_eq!(ids, [json!("0:7"), json!("1:7")]);
+
+        strategy.handle_response(r#"{"jsonrpc":"2.0","id":"1:7","result":{"roots":[]}}"#).await.unwrap();
+        let response = second.expect(|message| message.get("result").is_some()).await;
+        assert_eq!(response["id"], 7);
+
+        // Answered once, and never by a server that did not ask
+        assert!(strategy.handle_response(r#"{"jsonrpc":"2.0","id":"1:7","result":{}}"#).await.is_err());
+        assert!(strategy.handle_response(r#"{"jsonrpc":"2.0","id":"9:7","result":{}}"#).await.is_err());
+        while let Ok(message) = first.received.try_recv() {
+            assert!(message.get("result").is_none(), "first server got a reply: {}", message);
+        }
+    }
+}
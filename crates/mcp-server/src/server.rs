@@ -128,7 +128,7 @@ impl McpStdioServer {
             // We can't use self.send_response here due to borrowing issues
             // So we write directly to stdout
             if let Ok(json_str) = serde_json::to_string(&notification) {
-                print!("{}\n", json_str);
+                println!("{}", json_str);
                 let _ = io::stdout().flush();
             }
         } else if let Some(sender) = &self.log_sender {
@@ -238,7 +238,7 @@ impl McpServer for McpStdioServer {
         self.log_debug(&format!("Received message: {}", message)).await;
 
         let parsed: Value = serde_json::from_str(message)
-            .map_err(|e| mcp_types::McpError::Serialization(e))?;
+            .map_err(mcp_types::McpError::Serialization)?;
 
         // Check if it's a notification (no id field)
         if parsed.get("id").is_none() {
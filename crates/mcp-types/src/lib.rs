@@ -198,6 +198,15 @@ pub enum TransportType {
     /// Direct TCP socket transport for high-performance local connections
     #[serde(rename = "tcp")]
     Tcp,
+    /// Legacy HTTP+SSE transport from the 2024-11-05 protocol revision
+    #[serde(rename = "sse")]
+    Sse,
+    /// WebSocket transport (`ws://` or `wss://`) for servers behind WebSocket-only gateways
+    #[serde(rename = "websocket")]
+    WebSocket,
+    /// Unix domain socket transport for local daemons (`unix:/path` or `unix:@abstract-name`)
+    #[serde(rename = "unix")]
+    Unix,
 }
 
 /// Generic transport trait for MCP message communication.